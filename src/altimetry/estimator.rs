@@ -0,0 +1,241 @@
+//! Estimation of altitude and vertical velocity from barometric readings.
+//!
+//! [`AltitudeEstimator`] is a two state Kalman filter with a constant velocity model, the
+//! vertical acceleration is treated as white noise.
+use crate::devices::bmp390::compensation::Measurement;
+
+/// Standard atmosphere pressure at sea level in Pascal
+pub const SEA_LEVEL_PRESSURE: f32 = 101_325.0;
+
+/// Initial velocity variance in (m/s)², the vehicle is assumed to rest at the first reading
+/// but is given room to prove otherwise
+const INITIAL_VELOCITY_VARIANCE: f32 = 100.0;
+
+/// Converts a pressure into an altitude in meters with the barometric formula of the
+/// international standard atmosphere, valid up to 11km
+pub fn pressure_to_altitude(pressure: f32, sea_level_pressure: f32) -> f32 {
+	44_330.0 * (1.0 - libm::powf(pressure / sea_level_pressure, 1.0 / 5.255))
+}
+
+/// Tuning of the [`AltitudeEstimator`]
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct EstimatorConfig {
+	/// Standard deviation of the unmodelled vertical acceleration in m/s². Higher values
+	/// follow manoeuvres faster, lower values smooth more.
+	pub process_noise: f32,
+	/// Standard deviation of the barometric altitude in meters, see the noise figures of
+	/// [`crate::devices::bmp390::presets::Preset`]
+	pub measurement_noise: f32,
+	/// Pressure at the reference altitude in Pascal
+	pub sea_level_pressure: f32,
+}
+
+impl Default for EstimatorConfig {
+	fn default() -> Self {
+		Self {
+			process_noise: 2.0,
+			measurement_noise: 0.5,
+			sea_level_pressure: SEA_LEVEL_PRESSURE,
+		}
+	}
+}
+
+/// The filtered state after a reading
+#[derive(Clone, Copy, Default, PartialEq, Debug, defmt::Format)]
+pub struct Estimate {
+	/// Time of the reading in microseconds
+	pub time_us: u64,
+	/// Altitude in meters
+	pub altitude: f32,
+	/// Vertical velocity in m/s, positive upwards
+	pub velocity: f32,
+	/// Standard deviation of the altitude in meters
+	pub altitude_sigma: f32,
+	/// Standard deviation of the velocity in m/s
+	pub velocity_sigma: f32,
+}
+
+/// Kalman filter over altitude and vertical velocity
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct AltitudeEstimator {
+	config: EstimatorConfig,
+	/// Time of the last reading in microseconds
+	time_us: Option<u64>,
+	altitude: f32,
+	velocity: f32,
+	/// The symmetric covariance matrix [[p00, p01], [p01, p11]]
+	p00: f32,
+	p01: f32,
+	p11: f32,
+}
+
+impl Default for AltitudeEstimator {
+	fn default() -> Self {
+		Self::new(EstimatorConfig::default())
+	}
+}
+
+impl AltitudeEstimator {
+	pub fn new(config: EstimatorConfig) -> Self {
+		Self {
+			config,
+			time_us: None,
+			altitude: 0.0,
+			velocity: 0.0,
+			p00: 0.0,
+			p01: 0.0,
+			p11: 0.0,
+		}
+	}
+
+	pub fn config(&self) -> &EstimatorConfig {
+		&self.config
+	}
+
+	/// Changes the tuning, the current state is kept
+	pub fn set_config(&mut self, config: EstimatorConfig) {
+		self.config = config;
+	}
+
+	/// Forgets the state, the next reading starts the filter again
+	pub fn reset(&mut self) {
+		*self = Self::new(self.config);
+	}
+
+	/// Feeds a reading taken at `time_us`, for example from
+	/// [`crate::devices::bmp390::sensor_time::SensorClock::to_host_us`]
+	pub fn update(&mut self, time_us: u64, measurement: &Measurement) -> Estimate {
+		self.update_altitude(time_us, pressure_to_altitude(measurement.pressure, self.config.sea_level_pressure))
+	}
+
+	/// Feeds an altitude in meters measured at `time_us`.
+	///
+	/// Readings that are not newer than the previous one correct the state without
+	/// advancing it in time.
+	pub fn update_altitude(&mut self, time_us: u64, altitude: f32) -> Estimate {
+		let r = self.config.measurement_noise * self.config.measurement_noise;
+		let Some(last_us) = self.time_us else {
+			self.time_us = Some(time_us);
+			self.altitude = altitude;
+			self.velocity = 0.0;
+			self.p00 = r;
+			self.p01 = 0.0;
+			self.p11 = INITIAL_VELOCITY_VARIANCE;
+			return self.estimate();
+		};
+
+		if time_us > last_us {
+			self.predict((time_us - last_us) as f32 / 1_000_000.0);
+			self.time_us = Some(time_us);
+		}
+
+		let s = self.p00 + r;
+		let k0 = self.p00 / s;
+		let k1 = self.p01 / s;
+		let innovation = altitude - self.altitude;
+		self.altitude += k0 * innovation;
+		self.velocity += k1 * innovation;
+		self.p11 -= k1 * self.p01;
+		self.p00 *= 1.0 - k0;
+		self.p01 *= 1.0 - k0;
+		self.estimate()
+	}
+
+	/// Advances the state by `dt` seconds
+	fn predict(&mut self, dt: f32) {
+		let q = self.config.process_noise * self.config.process_noise;
+		let dt2 = dt * dt;
+		self.altitude += self.velocity * dt;
+		self.p00 += 2.0 * dt * self.p01 + dt2 * self.p11 + q * dt2 * dt2 / 4.0;
+		self.p01 += dt * self.p11 + q * dt2 * dt / 2.0;
+		self.p11 += q * dt2;
+	}
+
+	/// The current state, zero before the first reading
+	pub fn estimate(&self) -> Estimate {
+		Estimate {
+			time_us: self.time_us.unwrap_or_default(),
+			altitude: self.altitude,
+			velocity: self.velocity,
+			altitude_sigma: libm::sqrtf(self.p00),
+			velocity_sigma: libm::sqrtf(self.p11),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Deterministic noise, uniformly distributed in [-amplitude, amplitude]
+	struct Noise(u32);
+
+	impl Noise {
+		fn next(&mut self, amplitude: f32) -> f32 {
+			self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+			((self.0 >> 8) as f32 / (1 << 24) as f32 * 2.0 - 1.0) * amplitude
+		}
+	}
+
+	#[test]
+	fn test_pressure_to_altitude() {
+		assert_eq!(pressure_to_altitude(SEA_LEVEL_PRESSURE, SEA_LEVEL_PRESSURE), 0.0);
+		assert!((pressure_to_altitude(89_874.6, SEA_LEVEL_PRESSURE) - 1000.0).abs() < 1.0);
+		assert!(pressure_to_altitude(102_000.0, SEA_LEVEL_PRESSURE) < 0.0);
+	}
+
+	#[test]
+	fn test_stationary() {
+		let mut estimator = AltitudeEstimator::default();
+		let mut noise = Noise(1);
+		let mut estimate = Estimate::default();
+		for i in 0..500u64 {
+			estimate = estimator.update_altitude(i * 10_000, 120.0 + noise.next(0.8));
+		}
+		assert!((estimate.altitude - 120.0).abs() < 0.3, "{:?}", estimate);
+		assert!(estimate.velocity.abs() < 0.5, "{:?}", estimate);
+		assert!(estimate.altitude_sigma < 0.5);
+	}
+
+	#[test]
+	fn test_constant_climb() {
+		let mut estimator = AltitudeEstimator::default();
+		let mut noise = Noise(7);
+		let mut estimate = Estimate::default();
+		for i in 0..500u64 {
+			let t = i as f32 * 0.01;
+			estimate = estimator.update_altitude(i * 10_000, 10.0 * t + noise.next(0.8));
+		}
+		assert!((estimate.velocity - 10.0).abs() < 0.5, "{:?}", estimate);
+		assert!((estimate.altitude - 49.9).abs() < 0.5, "{:?}", estimate);
+	}
+
+	#[test]
+	fn test_parabolic_flight() {
+		// Thrown upwards with 50m/s, apogee after about 5.1s at 127m. The constant velocity
+		// model lags behind a constant deceleration unless the process noise is high enough.
+		let mut estimator = AltitudeEstimator::new(EstimatorConfig { process_noise: 50.0, ..Default::default() });
+		let mut noise = Noise(42);
+		for i in 0..800u64 {
+			let t = i as f32 * 0.01;
+			let estimate = estimator.update_altitude(i * 10_000, 50.0 * t - 4.905 * t * t + noise.next(0.5));
+			if i > 200 {
+				assert!((estimate.velocity - (50.0 - 9.81 * t)).abs() < 3.0, "{} {:?}", t, estimate);
+			}
+		}
+	}
+
+	#[test]
+	fn test_uncertainty() {
+		let mut estimator = AltitudeEstimator::default();
+		let first = estimator.update_altitude(0, 0.0);
+		assert_eq!(first.altitude_sigma, 0.5);
+		assert_eq!(first.velocity_sigma, 10.0);
+		let later = estimator.update_altitude(100_000, 0.0);
+		assert!(later.velocity_sigma < first.velocity_sigma);
+
+		// A reading from the past does not advance the state
+		let stale = estimator.update_altitude(50_000, 0.0);
+		assert_eq!(stale.time_us, 100_000);
+	}
+}
@@ -0,0 +1,366 @@
+//! Detection of the flight phases of a rocket from the output of the
+//! [`AltitudeEstimator`](super::estimator::AltitudeEstimator).
+use alloc::vec::Vec;
+use crate::altimetry::estimator::Estimate;
+use crate::packet::{DataPacket, FlightEventPacket};
+
+/// Weight of a new reading in the ground altitude average while on the pad
+const GROUND_SMOOTHING: f32 = 0.01;
+
+/// Thresholds and lockouts of the [`FlightDetector`]. Altitudes are relative to the ground
+/// altitude measured on the pad.
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct FlightConfig {
+	/// Upwards velocity in m/s that has to be exceeded for a launch
+	pub launch_velocity: f32,
+	/// Altitude in meters that has to be exceeded for a launch
+	pub launch_altitude: f32,
+	/// Drop of the velocity below its peak in m/s that marks the burnout
+	pub burnout_velocity_drop: f32,
+	/// Time after launch in microseconds before an apogee is accepted, covers pressure
+	/// disturbances of transonic flight and ejection charges
+	pub apogee_lockout_us: u64,
+	/// Fall below the peak altitude in meters that confirms the descent
+	pub descent_drop: f32,
+	/// Largest velocity in m/s that counts as standing still, for the landing and for
+	/// tracking the ground altitude on the pad
+	pub landing_velocity: f32,
+	/// Largest altitude in meters that counts as being on the ground for the landing
+	pub landing_altitude: f32,
+	/// Time in microseconds the vehicle has to stand still on the ground for the landing
+	pub landing_time_us: u64,
+	/// Number of consecutive readings a condition has to hold before the event is emitted
+	pub confirm_samples: u8,
+}
+
+impl Default for FlightConfig {
+	fn default() -> Self {
+		Self {
+			launch_velocity: 15.0,
+			launch_altitude: 10.0,
+			burnout_velocity_drop: 3.0,
+			apogee_lockout_us: 2_000_000,
+			descent_drop: 10.0,
+			landing_velocity: 1.0,
+			landing_altitude: 30.0,
+			landing_time_us: 3_000_000,
+			confirm_samples: 3,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, defmt::Format)]
+pub enum FlightPhase {
+	/// Waiting for the launch, the ground altitude is tracked while the vehicle stands still
+	#[default]
+	Pad,
+	/// The motor is burning
+	Boost,
+	/// The motor burned out, the vehicle is still climbing
+	Coast,
+	/// The vehicle passed the apogee, waiting for the descent to be confirmed
+	Apogee,
+	Descent,
+	Landed,
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum FlightEventKind {
+	Launch = 0x01,
+	Burnout = 0x02,
+	Apogee = 0x03,
+	Descent = 0x04,
+	Landing = 0x05,
+}
+
+/// A transition between two [`FlightPhase`]s
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct FlightEvent {
+	pub kind: FlightEventKind,
+	/// Time of the event in microseconds. For the apogee this is the time of the peak.
+	pub time_us: u64,
+	/// Altitude above ground in meters. For the apogee this is the peak altitude.
+	pub altitude: f32,
+	/// Vertical velocity in m/s
+	pub velocity: f32,
+}
+
+impl FlightEvent {
+	/// Frames the event for the telemetry link
+	pub fn serialize(&self) -> Vec<u8> {
+		FlightEventPacket {
+			event: self.kind as u8,
+			time_ms: (self.time_us / 1000) as u32,
+			altitude_cm: (self.altitude * 100.0) as i32,
+			velocity_cm_s: (self.velocity * 100.0) as i32,
+		}
+		.serialize()
+	}
+}
+
+/// State machine that turns altitude and velocity estimates into [`FlightEvent`]s
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct FlightDetector {
+	config: FlightConfig,
+	phase: FlightPhase,
+	ground: Option<f32>,
+	launch_us: u64,
+	/// Peak velocity during the boost in m/s
+	peak_velocity: f32,
+	/// Peak altitude above ground in meters and the time it was reached
+	peak: (f32, u64),
+	/// Number of consecutive readings the condition of the next event held
+	confirmed: u8,
+	/// Since when the landing condition holds
+	still_since: Option<u64>,
+}
+
+impl Default for FlightDetector {
+	fn default() -> Self {
+		Self::new(FlightConfig::default())
+	}
+}
+
+impl FlightDetector {
+	pub fn new(config: FlightConfig) -> Self {
+		Self {
+			config,
+			phase: FlightPhase::Pad,
+			ground: None,
+			launch_us: 0,
+			peak_velocity: 0.0,
+			peak: (0.0, 0),
+			confirmed: 0,
+			still_since: None,
+		}
+	}
+
+	pub fn phase(&self) -> FlightPhase {
+		self.phase
+	}
+
+	/// The altitude of the pad in meters, `None` before the first estimate
+	pub fn ground_altitude(&self) -> Option<f32> {
+		self.ground
+	}
+
+	/// The highest altitude above ground reached so far in meters
+	pub fn peak_altitude(&self) -> f32 {
+		self.peak.0
+	}
+
+	/// Starts over on the pad
+	pub fn reset(&mut self) {
+		*self = Self::new(self.config);
+	}
+
+	/// Counts a reading towards the next event, returns whether it is confirmed
+	fn confirm(&mut self, condition: bool) -> bool {
+		self.confirmed = if condition { self.confirmed.saturating_add(1) } else { 0 };
+		self.confirmed >= self.config.confirm_samples
+	}
+
+	fn transition(&mut self, phase: FlightPhase, kind: FlightEventKind, time_us: u64, altitude: f32, velocity: f32) -> Option<FlightEvent> {
+		self.phase = phase;
+		self.confirmed = 0;
+		Some(FlightEvent { kind, time_us, altitude, velocity })
+	}
+
+	/// Feeds the next estimate, returns the event it completed if any
+	pub fn update(&mut self, estimate: &Estimate) -> Option<FlightEvent> {
+		let ground = *self.ground.get_or_insert(estimate.altitude);
+		let altitude = estimate.altitude - ground;
+		let velocity = estimate.velocity;
+		let time_us = estimate.time_us;
+		if self.phase != FlightPhase::Pad && altitude > self.peak.0 {
+			self.peak = (altitude, time_us);
+		}
+
+		match self.phase {
+			FlightPhase::Pad => {
+				let launched = velocity > self.config.launch_velocity && altitude > self.config.launch_altitude;
+				if self.confirm(launched) {
+					self.launch_us = time_us;
+					self.peak_velocity = velocity;
+					self.peak = (altitude, time_us);
+					return self.transition(FlightPhase::Boost, FlightEventKind::Launch, time_us, altitude, velocity);
+				}
+				if velocity.abs() < self.config.landing_velocity {
+					self.ground = Some(ground + (estimate.altitude - ground) * GROUND_SMOOTHING);
+				}
+				None
+			}
+			FlightPhase::Boost => {
+				self.peak_velocity = self.peak_velocity.max(velocity);
+				if self.confirm(velocity < self.peak_velocity - self.config.burnout_velocity_drop) {
+					return self.transition(FlightPhase::Coast, FlightEventKind::Burnout, time_us, altitude, velocity);
+				}
+				None
+			}
+			FlightPhase::Coast => {
+				let unlocked = time_us.saturating_sub(self.launch_us) >= self.config.apogee_lockout_us;
+				if self.confirm(unlocked && velocity <= 0.0) {
+					let (altitude, time_us) = self.peak;
+					return self.transition(FlightPhase::Apogee, FlightEventKind::Apogee, time_us, altitude, 0.0);
+				}
+				None
+			}
+			FlightPhase::Apogee => {
+				if self.confirm(altitude < self.peak.0 - self.config.descent_drop) {
+					return self.transition(FlightPhase::Descent, FlightEventKind::Descent, time_us, altitude, velocity);
+				}
+				None
+			}
+			FlightPhase::Descent => {
+				let still = velocity.abs() < self.config.landing_velocity && altitude < self.config.landing_altitude;
+				if !still {
+					self.still_since = None;
+					return None;
+				}
+				let since = *self.still_since.get_or_insert(time_us);
+				if time_us.saturating_sub(since) >= self.config.landing_time_us {
+					return self.transition(FlightPhase::Landed, FlightEventKind::Landing, time_us, altitude, velocity);
+				}
+				None
+			}
+			FlightPhase::Landed => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::altimetry::estimator::{AltitudeEstimator, EstimatorConfig};
+
+	/// Sample interval of the traces in microseconds
+	const INTERVAL_US: u64 = 10_000;
+
+	/// Altitude trace of a model rocket flight on a 250m high field: 10s on the pad, 2s
+	/// boost at 60m/s², coast to the apogee at about 854m above the field, descent at 15m/s
+	/// under a drogue and 5m/s under the main below 300m, then 10s on the ground. Returns
+	/// `None` after the end.
+	fn flight_trace(i: u64) -> Option<f32> {
+		let t = (i * INTERVAL_US) as f32 / 1_000_000.0 - 10.0;
+		let burnout = (120.0f32, 120.0f32);
+		let apogee_t = 2.0 + burnout.1 / 9.81;
+		let apogee = burnout.0 + burnout.1 * burnout.1 / (2.0 * 9.81);
+		let main_t = apogee_t + (apogee - 300.0) / 15.0;
+		let landing_t = main_t + 300.0 / 5.0;
+
+		let altitude = if t < 0.0 {
+			0.0
+		} else if t < 2.0 {
+			30.0 * t * t
+		} else if t < apogee_t {
+			let dt = t - 2.0;
+			burnout.0 + burnout.1 * dt - 4.905 * dt * dt
+		} else if t < main_t {
+			apogee - 15.0 * (t - apogee_t)
+		} else if t < landing_t {
+			300.0 - 5.0 * (t - main_t)
+		} else if t < landing_t + 10.0 {
+			0.0
+		} else {
+			return None;
+		};
+		// Small deterministic ripple in place of sensor noise
+		let ripple = ((i * 7919) % 100) as f32 / 100.0 - 0.5;
+		Some(250.0 + altitude + ripple * 0.6)
+	}
+
+	fn fly(trace: impl Fn(u64) -> Option<f32>, config: FlightConfig) -> (Vec<FlightEvent>, FlightDetector) {
+		let mut estimator = AltitudeEstimator::new(EstimatorConfig { process_noise: 50.0, ..Default::default() });
+		let mut detector = FlightDetector::new(config);
+		let mut events = Vec::new();
+		let mut i = 0;
+		while let Some(altitude) = trace(i) {
+			let estimate = estimator.update_altitude(i * INTERVAL_US, altitude);
+			events.extend(detector.update(&estimate));
+			i += 1;
+		}
+		(events, detector)
+	}
+
+	#[test]
+	fn test_full_flight() {
+		let (events, detector) = fly(flight_trace, FlightConfig::default());
+		let kinds: Vec<FlightEventKind> = events.iter().map(|event| event.kind).collect();
+		assert_eq!(kinds, [
+			FlightEventKind::Launch,
+			FlightEventKind::Burnout,
+			FlightEventKind::Apogee,
+			FlightEventKind::Descent,
+			FlightEventKind::Landing,
+		]);
+		assert_eq!(detector.phase(), FlightPhase::Landed);
+		assert!((detector.ground_altitude().unwrap() - 250.0).abs() < 1.0);
+
+		let launch = &events[0];
+		assert!((10_000_000..10_900_000).contains(&launch.time_us), "{:?}", launch);
+		let burnout = &events[1];
+		assert!((12_000_000..13_000_000).contains(&burnout.time_us), "{:?}", burnout);
+		let apogee = &events[2];
+		assert!((apogee.time_us as i64 - 24_230_000).abs() < 500_000, "{:?}", apogee);
+		assert!((apogee.altitude - 854.0).abs() < 5.0, "{:?}", apogee);
+		assert!(events[3].time_us < apogee.time_us + 2_000_000);
+	}
+
+	#[test]
+	fn test_apogee_lockout() {
+		// Boost at 100m/s² for 1s after 1s on the pad. Shortly after the burnout a 120m dip
+		// of the barometric altitude mimics the pressure disturbance of transonic flight.
+		let trace = |i: u64| {
+			let t = (i * INTERVAL_US) as f32 / 1_000_000.0;
+			let altitude = if t < 1.0 {
+				0.0
+			} else if t < 2.0 {
+				50.0 * (t - 1.0) * (t - 1.0)
+			} else {
+				50.0 + 100.0 * (t - 2.0) - 4.905 * (t - 2.0) * (t - 2.0)
+			};
+			let dip = if (2.8..3.1).contains(&t) { 120.0 } else { 0.0 };
+			(t < 15.0).then_some(altitude - dip)
+		};
+
+		let (events, _) = fly(trace, FlightConfig::default());
+		assert_eq!(events[2].kind, FlightEventKind::Apogee);
+		assert!((events[2].time_us as i64 - 12_190_000).abs() < 500_000, "{:?}", events[2]);
+
+		// Without the lockout the dip is taken for the apogee
+		let config = FlightConfig { apogee_lockout_us: 0, ..Default::default() };
+		let (events, _) = fly(trace, config);
+		assert_eq!(events[2].kind, FlightEventKind::Apogee);
+		assert!(events[2].time_us < 3_200_000, "{:?}", events[2]);
+	}
+
+	#[test]
+	fn test_no_launch_on_pad() {
+		// Wind gusts and someone carrying the rocket up a 5m ladder
+		let trace = |i: u64| {
+			let t = (i * INTERVAL_US) as f32 / 1_000_000.0;
+			let gust = if (i / 50).is_multiple_of(7) { 2.0 } else { 0.0 };
+			(t < 60.0).then(|| 100.0 + gust + (t - 20.0).clamp(0.0, 5.0))
+		};
+		let (events, detector) = fly(trace, FlightConfig::default());
+		assert!(events.is_empty());
+		assert_eq!(detector.phase(), FlightPhase::Pad);
+	}
+
+	#[test]
+	fn test_event_packet() {
+		let event = FlightEvent {
+			kind: FlightEventKind::Apogee,
+			time_us: 24_230_000,
+			altitude: 854.25,
+			velocity: 0.0,
+		};
+		let packet = event.serialize();
+		assert_eq!(packet[..2], [0xb0, 0x0b]);
+		assert_eq!(packet[6], 0x02);
+		assert_eq!(packet[7], 13);
+		assert_eq!(packet[8], FlightEventKind::Apogee as u8);
+		assert_eq!(packet[packet.len() - 2..], [0xa0, 0x0a]);
+	}
+}
@@ -0,0 +1,6 @@
+//! This module contains sensor independent processing of barometric data, building on top
+//! of the drivers in [`crate::devices`].
+
+pub mod redundancy;
+pub mod estimator;
+pub mod flight;
@@ -0,0 +1,380 @@
+//! Fault tolerant fusion of several barometers measuring the same pressure.
+//!
+//! The [`Voter`] decides every round which sensors can be trusted and fuses their readings,
+//! [`RedundantBarometer`] drives it from an array of [`BMP390`]s. The voter can also be fed
+//! directly when the sensors are of different types or sit on different buses.
+#[cfg(feature = "async")]
+use embedded_registers::RegisterInterfaceAsync;
+#[cfg(feature = "sync")]
+use embedded_registers::RegisterInterfaceSync;
+#[cfg(feature = "async")]
+use crate::devices::bmp390::BMP390;
+#[cfg(feature = "sync")]
+use crate::devices::bmp390::BMP390Sync;
+use crate::devices::bmp390::{PRESSURE_RANGE, TEMPERATURE_RANGE};
+use crate::devices::bmp390::compensation::Measurement;
+use crate::devices::bmp390::error::Bmp390Error;
+
+/// Health of a single sensor as judged in the last round
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, defmt::Format)]
+pub enum SensorHealth {
+	/// Contributed to the fused reading
+	Healthy,
+	/// No reading has been received yet
+	#[default]
+	Unknown,
+	/// The last read failed, for example because the device no longer answers on the bus
+	Lost,
+	/// The device answered but reported an error, for example a fatal error or a timeout
+	Faulty,
+	/// The reading lies outside the operating range of the sensor
+	OutOfRange,
+	/// The pressure reading did not change for [`VoterConfig::stuck_limit`] rounds
+	Stuck,
+	/// The reading disagrees with the other sensors by more than [`VoterConfig::max_divergence`]
+	Diverging,
+}
+
+/// The outcome of one read attempt of a sensor
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub enum Sample {
+	Reading(Measurement),
+	/// The sensor answered but has no new measurement, its health is left unchanged
+	NotReady,
+	/// The bus transfer failed
+	Failed,
+	/// The sensor answered but reported an error
+	Faulty,
+}
+
+impl<E> From<Result<Measurement, Bmp390Error<E>>> for Sample {
+	fn from(result: Result<Measurement, Bmp390Error<E>>) -> Self {
+		match result {
+			Ok(measurement) => Sample::Reading(measurement),
+			Err(Bmp390Error::DataNotReady) => Sample::NotReady,
+			Err(Bmp390Error::Bus(_)) => Sample::Failed,
+			Err(_) => Sample::Faulty,
+		}
+	}
+}
+
+/// Thresholds of the [`Voter`]
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct VoterConfig {
+	/// Largest accepted deviation from the consensus pressure in Pascal. The absolute
+	/// accuracy of the BMP390 is ±50Pa, so two good sensors may disagree by up to 100Pa.
+	pub max_divergence: f32,
+	/// Number of consecutive identical pressure readings after which a sensor is stuck
+	pub stuck_limit: u16,
+}
+
+impl Default for VoterConfig {
+	fn default() -> Self {
+		Self {
+			max_divergence: 150.0,
+			stuck_limit: 10,
+		}
+	}
+}
+
+/// Bookkeeping of a single sensor
+#[derive(Clone, Copy, Default, PartialEq, Debug, defmt::Format)]
+pub struct SensorStatus {
+	pub health: SensorHealth,
+	/// The last reading received, regardless of its health
+	pub last: Option<Measurement>,
+	/// Number of failed reads since the voter was created
+	pub failures: u32,
+	/// Number of consecutive rounds with an unchanged pressure reading
+	repeats: u16,
+}
+
+/// The fused reading of one round
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct Vote {
+	/// Mean of all healthy sensors, `None` if no sensor could be trusted
+	pub measurement: Option<Measurement>,
+	/// Number of sensors that contributed to [`Self::measurement`]
+	pub healthy: usize,
+}
+
+/// Judges the health of `N` sensors and fuses the readings of the healthy ones.
+///
+/// Each round a sensor is checked for failed reads, readings outside the operating range
+/// and stuck values. The remaining sensors are compared against a consensus, which is the
+/// median with three or more sensors. Two sensors can only be compared with each other, if
+/// they disagree the one closer to the last fused pressure is trusted.
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct Voter<const N: usize> {
+	config: VoterConfig,
+	status: [SensorStatus; N],
+	last: Option<Measurement>,
+}
+
+impl<const N: usize> Default for Voter<N> {
+	fn default() -> Self {
+		Self::new(VoterConfig::default())
+	}
+}
+
+impl<const N: usize> Voter<N> {
+	pub fn new(config: VoterConfig) -> Self {
+		Self {
+			config,
+			status: [SensorStatus::default(); N],
+			last: None,
+		}
+	}
+
+	/// Per sensor health, in the order the samples are passed to [`Self::update`]
+	pub fn status(&self) -> &[SensorStatus; N] {
+		&self.status
+	}
+
+	/// The most recent fused reading
+	pub fn last(&self) -> Option<Measurement> {
+		self.last
+	}
+
+	/// Judges the samples of one round and fuses the healthy ones
+	pub fn update(&mut self, samples: &[Sample; N]) -> Vote {
+		let mut candidates = [false; N];
+		for (i, sample) in samples.iter().enumerate() {
+			let status = &mut self.status[i];
+			let measurement = match sample {
+				Sample::Reading(measurement) => *measurement,
+				Sample::NotReady => continue,
+				Sample::Failed => {
+					status.health = SensorHealth::Lost;
+					status.failures = status.failures.saturating_add(1);
+					continue;
+				}
+				Sample::Faulty => {
+					status.health = SensorHealth::Faulty;
+					status.failures = status.failures.saturating_add(1);
+					continue;
+				}
+			};
+
+			if status.last.is_some_and(|last| last.pressure == measurement.pressure) {
+				status.repeats = status.repeats.saturating_add(1);
+			} else {
+				status.repeats = 0;
+			}
+			status.last = Some(measurement);
+
+			status.health = if !in_range(&measurement) {
+				SensorHealth::OutOfRange
+			} else if status.repeats >= self.config.stuck_limit {
+				SensorHealth::Stuck
+			} else {
+				candidates[i] = true;
+				SensorHealth::Healthy
+			};
+		}
+
+		if let Some(reference) = self.consensus(&candidates) {
+			for (i, candidate) in candidates.iter_mut().enumerate() {
+				let pressure = self.status[i].last.map_or(reference, |last| last.pressure);
+				if *candidate && (pressure - reference).abs() > self.config.max_divergence {
+					*candidate = false;
+					self.status[i].health = SensorHealth::Diverging;
+				}
+			}
+		}
+
+		let mut sum = Measurement::default();
+		let mut healthy = 0;
+		for status in self.status.iter().zip(candidates).filter_map(|(status, candidate)| candidate.then_some(status)) {
+			let measurement = status.last.unwrap_or_default();
+			sum.pressure += measurement.pressure;
+			sum.temperature += measurement.temperature;
+			healthy += 1;
+		}
+
+		let measurement = (healthy > 0).then(|| Measurement {
+			pressure: sum.pressure / healthy as f32,
+			temperature: sum.temperature / healthy as f32,
+		});
+		if measurement.is_some() {
+			self.last = measurement;
+		}
+		Vote { measurement, healthy }
+	}
+
+	/// The pressure the candidates are compared against
+	fn consensus(&self, candidates: &[bool; N]) -> Option<f32> {
+		let mut pressures = [0.0f32; N];
+		let mut count = 0;
+		for (status, _) in self.status.iter().zip(candidates).filter(|(_, candidate)| **candidate) {
+			pressures[count] = status.last.map_or(0.0, |last| last.pressure);
+			count += 1;
+		}
+		let pressures = &mut pressures[..count];
+		pressures.sort_unstable_by(f32::total_cmp);
+
+		match pressures {
+			[] => None,
+			[only] => Some(*only),
+			[a, b] if (*b - *a) > self.config.max_divergence => match self.last {
+				Some(last) if (*a - last.pressure).abs() <= (*b - last.pressure).abs() => Some(*a),
+				Some(_) => Some(*b),
+				None => Some((*a + *b) / 2.0),
+			},
+			[a, b] => Some((*a + *b) / 2.0),
+			_ if count % 2 == 0 => Some((pressures[count / 2 - 1] + pressures[count / 2]) / 2.0),
+			_ => Some(pressures[count / 2]),
+		}
+	}
+}
+
+fn in_range(measurement: &Measurement) -> bool {
+	(PRESSURE_RANGE.0..=PRESSURE_RANGE.1).contains(&measurement.pressure)
+		&& (TEMPERATURE_RANGE.0..=TEMPERATURE_RANGE.1).contains(&measurement.temperature)
+}
+
+/// `N` [`BMP390`]s measuring the same pressure, fused by a [`Voter`]. With the `sync` feature
+/// a blocking flavour is available as `RedundantBarometerSync`.
+///
+/// A sensor that drops off the bus is reported as [`SensorHealth::Lost`] while the others
+/// carry on, it is read again every round and rejoins once it answers. Enable
+/// [`BMP390::set_auto_recover`] on the sensors so that a sensor coming back from a power
+/// loss is configured again.
+#[maybe_async_cfg::maybe(
+	idents(BMP390(async), RegisterInterface, RedundantBarometer(async)),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+pub struct RedundantBarometer<I: RegisterInterface, const N: usize> {
+	sensors: [BMP390<I>; N],
+	voter: Voter<N>,
+}
+
+#[maybe_async_cfg::maybe(
+	idents(BMP390(async), RegisterInterface, RedundantBarometer(async)),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface, const N: usize> RedundantBarometer<I, N> {
+	/// Takes initialized and configured sensors
+	pub fn new(sensors: [BMP390<I>; N], config: VoterConfig) -> Self {
+		Self {
+			sensors,
+			voter: Voter::new(config),
+		}
+	}
+
+	/// Reads all sensors once and returns the fused reading
+	pub async fn read(&mut self) -> Vote {
+		let mut samples = [Sample::NotReady; N];
+		for (sample, sensor) in samples.iter_mut().zip(self.sensors.iter_mut()) {
+			*sample = sensor.read_measurement().await.into();
+		}
+		self.voter.update(&samples)
+	}
+
+	/// Per sensor health as judged by the last [`Self::read`]
+	pub fn status(&self) -> &[SensorStatus; N] {
+		self.voter.status()
+	}
+
+	pub fn sensors(&mut self) -> &mut [BMP390<I>; N] {
+		&mut self.sensors
+	}
+
+	/// Returns the sensors
+	pub fn release(self) -> [BMP390<I>; N] {
+		self.sensors
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn reading(pressure: f32) -> Sample {
+		Sample::Reading(Measurement { pressure, temperature: 20.0 })
+	}
+
+	#[test]
+	fn test_average_of_healthy_sensors() {
+		let mut voter = Voter::<2>::default();
+		let vote = voter.update(&[reading(100_000.0), reading(100_040.0)]);
+		assert_eq!(vote.healthy, 2);
+		assert_eq!(vote.measurement.unwrap().pressure, 100_020.0);
+		assert!(voter.status().iter().all(|status| status.health == SensorHealth::Healthy));
+	}
+
+	#[test]
+	fn test_lost_sensor() {
+		let mut voter = Voter::<2>::default();
+		voter.update(&[reading(100_000.0), reading(100_040.0)]);
+		let vote = voter.update(&[Sample::Failed, reading(100_041.0)]);
+		assert_eq!(vote.healthy, 1);
+		assert_eq!(vote.measurement.unwrap().pressure, 100_041.0);
+		assert_eq!(voter.status()[0].health, SensorHealth::Lost);
+		assert_eq!(voter.status()[0].failures, 1);
+
+		// The sensor rejoins once it answers again, not ready leaves it untouched
+		voter.update(&[reading(100_001.0), Sample::NotReady]);
+		assert_eq!(voter.status()[0].health, SensorHealth::Healthy);
+		assert_eq!(voter.status()[1].health, SensorHealth::Healthy);
+	}
+
+	#[test]
+	fn test_error_mapping() {
+		assert_eq!(Sample::from(Err::<Measurement, _>(Bmp390Error::Bus(()))), Sample::Failed);
+		assert_eq!(Sample::from(Err::<Measurement, Bmp390Error<()>>(Bmp390Error::DataNotReady)), Sample::NotReady);
+		assert_eq!(Sample::from(Err::<Measurement, Bmp390Error<()>>(Bmp390Error::Fatal)), Sample::Faulty);
+
+		let mut voter = Voter::<2>::default();
+		voter.update(&[Sample::Faulty, reading(100_000.0)]);
+		assert_eq!(voter.status()[0].health, SensorHealth::Faulty);
+		assert_eq!(voter.status()[0].failures, 1);
+	}
+
+	#[test]
+	fn test_out_of_range() {
+		let mut voter = Voter::<2>::default();
+		let vote = voter.update(&[reading(0.0), reading(100_000.0)]);
+		assert_eq!(voter.status()[0].health, SensorHealth::OutOfRange);
+		assert_eq!(vote.measurement.unwrap().pressure, 100_000.0);
+	}
+
+	#[test]
+	fn test_stuck_sensor() {
+		let mut voter = Voter::<2>::new(VoterConfig { stuck_limit: 3, ..Default::default() });
+		for i in 0..3 {
+			voter.update(&[reading(100_000.0), reading(100_000.0 + i as f32)]);
+			assert_eq!(voter.status()[0].health, SensorHealth::Healthy);
+		}
+		let vote = voter.update(&[reading(100_000.0), reading(100_003.0)]);
+		assert_eq!(voter.status()[0].health, SensorHealth::Stuck);
+		assert_eq!(vote.measurement.unwrap().pressure, 100_003.0);
+	}
+
+	#[test]
+	fn test_majority_vote() {
+		let mut voter = Voter::<3>::default();
+		let vote = voter.update(&[reading(100_000.0), reading(95_000.0), reading(100_020.0)]);
+		assert_eq!(voter.status()[1].health, SensorHealth::Diverging);
+		assert_eq!(vote.healthy, 2);
+		assert_eq!(vote.measurement.unwrap().pressure, 100_010.0);
+	}
+
+	#[test]
+	fn test_two_sensors_diverging() {
+		let mut voter = Voter::<2>::default();
+		voter.update(&[reading(100_000.0), reading(100_010.0)]);
+		let vote = voter.update(&[reading(100_020.0), reading(90_000.0)]);
+		assert_eq!(voter.status()[0].health, SensorHealth::Healthy);
+		assert_eq!(voter.status()[1].health, SensorHealth::Diverging);
+		assert_eq!(vote.measurement.unwrap().pressure, 100_020.0);
+
+		// Without a previous consensus there is nothing to decide with
+		let mut voter = Voter::<2>::default();
+		let vote = voter.update(&[reading(100_000.0), reading(90_000.0)]);
+		assert_eq!(vote.measurement, None);
+		assert!(voter.status().iter().all(|status| status.health == SensorHealth::Diverging));
+	}
+}
@@ -7,4 +7,16 @@ pub enum LogicLevel {
 	High = 1,
 	#[default]
 	Low = 0
-}
\ No newline at end of file
+}
+
+impl From<bool> for LogicLevel {
+	fn from(high: bool) -> Self {
+		if high { LogicLevel::High } else { LogicLevel::Low }
+	}
+}
+
+impl From<LogicLevel> for bool {
+	fn from(level: LogicLevel) -> Self {
+		level == LogicLevel::High
+	}
+}
@@ -0,0 +1,119 @@
+use crate::devices::bmp390::registers::CalibrationICoefficients;
+
+/// A compensated reading of the sensor
+#[derive(Clone, Copy, Default, PartialEq, Debug, defmt::Format)]
+pub struct Measurement {
+	/// Pressure in Pascal
+	pub pressure: f32,
+	/// Temperature in degrees Celsius
+	pub temperature: f32,
+}
+
+/// A compensated reading together with the sensor time at which it was read out
+#[derive(Clone, Copy, Default, PartialEq, Debug, defmt::Format)]
+pub struct TimedMeasurement {
+	pub measurement: Measurement,
+	/// Sensor time extended to a monotonic tick count, see
+	/// [`crate::devices::bmp390::sensor_time::SensorClock::extend`]
+	pub sensor_time: u64,
+}
+
+/// The trimming coefficients of the device converted to floating point, as described
+/// in datasheet section 8.4
+#[derive(Clone, Copy, Default, PartialEq, Debug, defmt::Format)]
+pub struct Calibration {
+	par_t1: f32,
+	par_t2: f32,
+	par_t3: f32,
+	par_p1: f32,
+	par_p2: f32,
+	par_p3: f32,
+	par_p4: f32,
+	par_p5: f32,
+	par_p6: f32,
+	par_p7: f32,
+	par_p8: f32,
+	par_p9: f32,
+	par_p10: f32,
+	par_p11: f32,
+}
+
+/// 2^n for the scaling factors used by the datasheet, which go up to 2^65
+fn pow2(n: i32) -> f32 {
+	libm::ldexpf(1.0, n)
+}
+
+impl From<&CalibrationICoefficients> for Calibration {
+	fn from(reg: &CalibrationICoefficients) -> Self {
+		Self {
+			par_t1: reg.read_par_t1() as f32 / pow2(-8),
+			par_t2: reg.read_par_t2() as f32 / pow2(30),
+			par_t3: reg.read_par_t3() as f32 / pow2(48),
+			par_p1: (reg.read_par_p1() as f32 - pow2(14)) / pow2(20),
+			par_p2: (reg.read_par_p2() as f32 - pow2(14)) / pow2(29),
+			par_p3: reg.read_par_p3() as f32 / pow2(32),
+			par_p4: reg.read_par_p4() as f32 / pow2(37),
+			par_p5: reg.read_par_p5() as f32 / pow2(-3),
+			par_p6: reg.read_par_p6() as f32 / pow2(6),
+			par_p7: reg.read_par_p7() as f32 / pow2(8),
+			par_p8: reg.read_par_p8() as f32 / pow2(15),
+			par_p9: reg.read_par_p9() as f32 / pow2(48),
+			par_p10: reg.read_par_p10() as f32 / pow2(48),
+			par_p11: reg.read_par_p11() as f32 / pow2(65),
+		}
+	}
+}
+
+impl Calibration {
+	/// Converts the raw 24-bit readings into a [`Measurement`]
+	pub fn compensate(&self, raw_pressure: u32, raw_temperature: u32) -> Measurement {
+		let temperature = self.compensate_temperature(raw_temperature);
+		Measurement {
+			pressure: self.compensate_pressure(raw_pressure, temperature),
+			temperature,
+		}
+	}
+
+	fn compensate_temperature(&self, raw_temperature: u32) -> f32 {
+		let partial_data1 = raw_temperature as f32 - self.par_t1;
+		let partial_data2 = partial_data1 * self.par_t2;
+		partial_data2 + partial_data1 * partial_data1 * self.par_t3
+	}
+
+	fn compensate_pressure(&self, raw_pressure: u32, t_lin: f32) -> f32 {
+		let t_lin2 = t_lin * t_lin;
+		let t_lin3 = t_lin2 * t_lin;
+		let raw = raw_pressure as f32;
+		let raw2 = raw * raw;
+
+		let partial_out1 = self.par_p5 + self.par_p6 * t_lin + self.par_p7 * t_lin2 + self.par_p8 * t_lin3;
+		let partial_out2 = raw * (self.par_p1 + self.par_p2 * t_lin + self.par_p3 * t_lin2 + self.par_p4 * t_lin3);
+		let partial_data4 = raw2 * (self.par_p9 + self.par_p10 * t_lin) + raw2 * raw * self.par_p11;
+
+		partial_out1 + partial_out2 + partial_data4
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_compensate() {
+		// Trimming coefficients of a BMP390, the expected values follow from the datasheet
+		// formulas evaluated in double precision
+		let reg = CalibrationICoefficients {
+			data: [
+				0xA2, 0x6B, 0x0B, 0x49, 0xF9, 0xFC, 0xFB, 0x60, 0xF3, 0x23, 0x01,
+				0x96, 0x4D, 0xD6, 0x5C, 0x03, 0xF9, 0x93, 0x3E, 0x04, 0xC9,
+			],
+		};
+		let calibration = Calibration::from(&reg);
+		assert_eq!(calibration.par_t1, 27554.0 * 256.0);
+		assert_eq!(calibration.par_p11, -55.0 / 36_893_488_147_419_103_232.0);
+
+		let measurement = calibration.compensate(6_500_000, 8_200_000);
+		assert!((measurement.temperature - 19.927_756).abs() < 1e-3, "{}", measurement.temperature);
+		assert!((measurement.pressure - 55_659.78).abs() < 0.5, "{}", measurement.pressure);
+	}
+}
@@ -0,0 +1,251 @@
+use crate::common::enums::LogicLevel;
+use crate::devices::bmp390::enums::{IIRFilter, InteruptOutput, OutputDataRate, OversamplingSetting, PowerMode, WatchdogTimeout};
+
+/// Interrupt pin and interrupt source configuration
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, defmt::Format)]
+pub struct InterruptConfig {
+	/// Interrupt when temperature and pressure data is ready
+	pub data_ready: bool,
+	/// Interrupt when the FIFO buffer is full
+	pub fifo_full: bool,
+	/// Interrupt when the FIFO buffer reaches the watermark
+	pub fifo_watermark: bool,
+	/// Latch the INT pin and INT_STATUS register until the status is read
+	pub latch: bool,
+	/// Whether the interrupt pin is active high or low
+	pub active_level: LogicLevel,
+	/// Whether the interrupt pin is push pull or open drain
+	pub output: InteruptOutput,
+}
+
+/// Serial interface configuration, as read from the `IFConf` register
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, defmt::Format)]
+pub struct InterfaceConfig {
+	/// SPI operates in 3-wire mode
+	pub spi_3wire: bool,
+	/// The I2C watchdog is enabled
+	pub i2c_watchdog: bool,
+	pub i2c_watchdog_timeout: WatchdogTimeout,
+}
+
+/// The complete measurement configuration of a [`super::BMP390`].
+///
+/// Build it with the builder methods, check it with [`Self::validate`] and apply it in
+/// one go with [`super::BMP390::apply_config`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub struct Bmp390Config {
+	pub power_mode: PowerMode,
+	pub enable_press: bool,
+	pub enable_temp: bool,
+	pub temp_oversample: OversamplingSetting,
+	pub press_oversample: OversamplingSetting,
+	pub output_data_rate: OutputDataRate,
+	pub iir_filter: IIRFilter,
+	pub interrupt: InterruptConfig,
+}
+
+impl Default for Bmp390Config {
+	/// Matches the power-on reset state of the device, except both sensors are enabled
+	fn default() -> Self {
+		Self {
+			power_mode: PowerMode::Sleep,
+			enable_press: true,
+			enable_temp: true,
+			temp_oversample: OversamplingSetting::X1,
+			press_oversample: OversamplingSetting::X1,
+			output_data_rate: OutputDataRate::Hz200,
+			iir_filter: IIRFilter::Coeff0,
+			interrupt: InterruptConfig::default(),
+		}
+	}
+}
+
+/// FIFO configuration, written with [`super::BMP390::set_fifo_config`].
+///
+/// [`super::BMP390::read_fifo`] only returns frames holding both pressure and temperature.
+/// Such a frame takes 7 bytes of the 512 byte FIFO.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub struct FifoConfig {
+	pub enable: bool,
+	/// Stop writing once the FIFO is full instead of dropping the oldest frames
+	pub stop_on_full: bool,
+	pub pressure: bool,
+	pub temperature: bool,
+	/// Append a sensor time frame once the FIFO has been emptied
+	pub sensor_time: bool,
+	/// Store only every 2^`subsampling`th measurement, 0 to 7
+	pub subsampling: u8,
+	/// Store the IIR filtered data instead of the unfiltered data
+	pub filtered: bool,
+	/// Fill level in bytes that raises the watermark interrupt, up to 511
+	pub watermark: u16,
+}
+
+impl Default for FifoConfig {
+	/// Pressure, temperature and sensor time of every measurement, with the watermark at
+	/// 72 frames
+	fn default() -> Self {
+		Self {
+			enable: true,
+			stop_on_full: false,
+			pressure: true,
+			temperature: true,
+			sensor_time: true,
+			subsampling: 0,
+			filtered: false,
+			watermark: 504,
+		}
+	}
+}
+
+impl FifoConfig {
+	pub fn validate(&self) -> Result<(), InvalidConfig> {
+		if self.subsampling > 7 {
+			return Err(InvalidConfig::FifoSubsamplingOutOfRange);
+		}
+		if self.watermark > 511 {
+			return Err(InvalidConfig::FifoWatermarkOutOfRange);
+		}
+		Ok(())
+	}
+}
+
+/// Reasons a [`Bmp390Config`] or [`FifoConfig`] can be refused before it is written to the
+/// device
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum InvalidConfig {
+	/// A raw subdivision factor is larger than 17
+	OutputDataRateOutOfRange,
+	/// The measurement with the chosen oversampling takes longer than one ODR period
+	OutputDataRateTooFast,
+	/// The FIFO subsampling exponent is larger than 7
+	FifoSubsamplingOutOfRange,
+	/// The FIFO watermark is larger than 511 bytes
+	FifoWatermarkOutOfRange,
+}
+
+impl Bmp390Config {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn power_mode(mut self, mode: PowerMode) -> Self {
+		self.power_mode = mode;
+		self
+	}
+
+	pub fn enable(mut self, enable_press: bool, enable_temp: bool) -> Self {
+		self.enable_press = enable_press;
+		self.enable_temp = enable_temp;
+		self
+	}
+
+	pub fn oversampling(mut self, temp_oversample: OversamplingSetting, press_oversample: OversamplingSetting) -> Self {
+		self.temp_oversample = temp_oversample;
+		self.press_oversample = press_oversample;
+		self
+	}
+
+	pub fn output_data_rate(mut self, output_data_rate: OutputDataRate) -> Self {
+		self.output_data_rate = output_data_rate;
+		self
+	}
+
+	pub fn iir_filter(mut self, coef: IIRFilter) -> Self {
+		self.iir_filter = coef;
+		self
+	}
+
+	pub fn interrupt(mut self, interrupt: InterruptConfig) -> Self {
+		self.interrupt = interrupt;
+		self
+	}
+
+	/// Worst case duration of one measurement in microseconds (datasheet section 3.9.2)
+	pub fn measurement_time_us(&self) -> u32 {
+		let mut time = 234;
+		if self.enable_press {
+			time += 392 + (1 << self.press_oversample as u32) * 2020;
+		}
+		if self.enable_temp {
+			time += 163 + (1 << self.temp_oversample as u32) * 2020;
+		}
+		time
+	}
+
+	/// Checks the configuration against the oversampling/ODR compatibility table.
+	///
+	/// In normal mode a measurement has to finish within one ODR period, otherwise the
+	/// device refuses the configuration with `conf_err`.
+	pub fn validate(&self) -> Result<(), InvalidConfig> {
+		if self.power_mode == PowerMode::Normal && self.measurement_time_us() > self.output_data_rate.period_us() {
+			return Err(InvalidConfig::OutputDataRateTooFast);
+		}
+		Ok(())
+	}
+}
+
+impl OutputDataRate {
+	/// The fastest rate at which a measurement of both pressure and temperature with the
+	/// given oversampling finishes within one period
+	pub fn fastest_for(temp_oversample: OversamplingSetting, press_oversample: OversamplingSetting) -> Self {
+		let measurement_time = Bmp390Config::new()
+			.oversampling(temp_oversample, press_oversample)
+			.measurement_time_us();
+		Self::ALL.into_iter()
+			.find(|odr| odr.period_us() >= measurement_time)
+			.unwrap_or(Self::Hz0p0015)
+	}
+}
+
+impl TryFrom<u8> for OutputDataRate {
+	type Error = InvalidConfig;
+
+	/// Converts a raw subdivision factor, rejecting values above 17
+	fn try_from(subdivision: u8) -> Result<Self, Self::Error> {
+		Self::ALL.get(subdivision as usize).copied().ok_or(InvalidConfig::OutputDataRateOutOfRange)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_compatibility_table() {
+		// Recommended settings from datasheet table 10 with their fastest ODR
+		let table = [
+			(OversamplingSetting::X1, OversamplingSetting::X1, OutputDataRate::Hz200),
+			(OversamplingSetting::X1, OversamplingSetting::X2, OutputDataRate::Hz100),
+			(OversamplingSetting::X1, OversamplingSetting::X4, OutputDataRate::Hz50),
+			(OversamplingSetting::X1, OversamplingSetting::X8, OutputDataRate::Hz50),
+			(OversamplingSetting::X2, OversamplingSetting::X16, OutputDataRate::Hz25),
+			(OversamplingSetting::X2, OversamplingSetting::X32, OutputDataRate::Hz12p5),
+		];
+		for (temp, press, odr) in table {
+			let config = Bmp390Config::new()
+				.power_mode(PowerMode::Normal)
+				.oversampling(temp, press)
+				.output_data_rate(odr);
+			assert_eq!(config.validate(), Ok(()));
+			assert_eq!(OutputDataRate::fastest_for(temp, press), odr);
+			if let Some(faster) = odr.subdivision().checked_sub(1) {
+				let faster = OutputDataRate::try_from(faster).unwrap();
+				assert_eq!(config.output_data_rate(faster).validate(), Err(InvalidConfig::OutputDataRateTooFast));
+			}
+		}
+	}
+
+	#[test]
+	fn test_output_data_rate_range() {
+		assert_eq!(OutputDataRate::try_from(17), Ok(OutputDataRate::Hz0p0015));
+		assert_eq!(OutputDataRate::try_from(18), Err(InvalidConfig::OutputDataRateOutOfRange));
+	}
+
+	#[test]
+	fn test_output_data_rate_units() {
+		assert_eq!(OutputDataRate::Hz200.period_us(), 5_000);
+		assert_eq!(OutputDataRate::Hz12p5.hz(), 12.5);
+		assert_eq!(OutputDataRate::Hz0p0015.period_us(), 655_360_000);
+	}
+}
@@ -49,4 +49,66 @@ pub enum IIRFilter {
 	Coeff63= 0b110,
 	Coeff127= 0b111,
 
-}
\ No newline at end of file
+}
+
+
+/// Timeout of the I2C watchdog
+#[derive(BitfieldEnum, Copy, Clone, Default, PartialEq, Eq, Debug, defmt::Format)]
+#[bondrewd_enum(u8)]
+pub enum WatchdogTimeout {
+	/// 1.25 ms
+	#[default]
+	Short = 0,
+	/// 40 ms
+	Long = 1,
+}
+
+
+/// The output data rate in normal mode, 200Hz divided by a power of two
+#[derive(BitfieldEnum, Copy, Clone, Default, PartialEq, Eq, Debug, defmt::Format)]
+#[bondrewd_enum(u8)]
+pub enum OutputDataRate {
+	#[default]
+	Hz200 = 0,
+	Hz100 = 1,
+	Hz50 = 2,
+	Hz25 = 3,
+	Hz12p5 = 4,
+	Hz6p25 = 5,
+	Hz3p1 = 6,
+	Hz1p5 = 7,
+	Hz0p78 = 8,
+	Hz0p39 = 9,
+	Hz0p2 = 10,
+	Hz0p1 = 11,
+	Hz0p05 = 12,
+	Hz0p02 = 13,
+	Hz0p01 = 14,
+	Hz0p006 = 15,
+	Hz0p003 = 16,
+	Hz0p0015 = 17,
+}
+
+impl OutputDataRate {
+	/// All rates from the fastest to the slowest
+	pub const ALL: [OutputDataRate; 18] = [
+		Self::Hz200, Self::Hz100, Self::Hz50, Self::Hz25, Self::Hz12p5, Self::Hz6p25,
+		Self::Hz3p1, Self::Hz1p5, Self::Hz0p78, Self::Hz0p39, Self::Hz0p2, Self::Hz0p1,
+		Self::Hz0p05, Self::Hz0p02, Self::Hz0p01, Self::Hz0p006, Self::Hz0p003, Self::Hz0p0015,
+	];
+
+	/// The subdivision factor written to the ODR register, the rate is 200Hz / 2^value
+	pub fn subdivision(self) -> u8 {
+		self as u8
+	}
+
+	/// The exact rate in Hz
+	pub fn hz(self) -> f32 {
+		200.0 / (1u32 << self.subdivision()) as f32
+	}
+
+	/// The time between two measurements in microseconds
+	pub fn period_us(self) -> u32 {
+		5000 << self.subdivision()
+	}
+}
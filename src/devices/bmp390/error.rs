@@ -0,0 +1,27 @@
+use crate::devices::bmp390::config::InvalidConfig;
+
+/// Errors reported by the [`super::BMP390`] driver.
+///
+/// Besides errors of the underlying bus this carries the device level conditions,
+/// including the flags of the `Error` register.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum Bmp390Error<E> {
+	/// The bus reported an error
+	Bus(E),
+	/// The device answered with an unexpected chip id
+	WrongChipId(u8),
+	/// The configuration was refused before it was written to the device
+	InvalidConfig(InvalidConfig),
+	/// The device flagged `conf_err`, the sensor configuration is invalid
+	Configuration,
+	/// The device flagged `cmd_err`, a command failed
+	Command,
+	/// The device flagged `fatal_err`
+	Fatal,
+	/// No new data was available when it was read
+	DataNotReady,
+	/// The device did not respond in time
+	Timeout,
+	/// Waiting on the interrupt pin failed
+	InterruptPin,
+}
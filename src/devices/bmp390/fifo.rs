@@ -0,0 +1,178 @@
+#[cfg(feature = "async")]
+use embedded_registers::RegisterInterfaceAsync;
+#[cfg(feature = "sync")]
+use embedded_registers::RegisterInterfaceSync;
+#[cfg(feature = "async")]
+use crate::devices::bmp390::BMP390;
+#[cfg(feature = "sync")]
+use crate::devices::bmp390::BMP390Sync;
+use crate::devices::bmp390::compensation::Measurement;
+use crate::devices::bmp390::config::FifoConfig;
+use crate::devices::bmp390::error::Bmp390Error;
+use crate::devices::bmp390::registers::{FIFOConfig1, FIFOConfig2, FIFOData, FIFOLength, FIFOPayload, FIFOPressureTemperature, FIFOWatermark};
+
+// FIFO frame headers, see datasheet section 3.6.2
+const FRAME_PRESSURE_TEMPERATURE: u8 = 0x94;
+const FRAME_TEMPERATURE: u8 = 0x90;
+const FRAME_PRESSURE: u8 = 0x84;
+const FRAME_SENSOR_TIME: u8 = 0xA0;
+const FRAME_CONFIG_CHANGE: u8 = 0x48;
+const FRAME_CONFIG_ERROR: u8 = 0x44;
+
+#[maybe_async_cfg::maybe(
+	idents(BMP390(async), RegisterInterface),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> BMP390<I> {
+	/// Validates and writes the FIFO configuration. Accepted configurations are remembered
+	/// and re-applied when a power-on reset is detected.
+	pub async fn set_fifo_config(&mut self, config: &FifoConfig) -> Result<(), Bmp390Error<I::Error>> {
+		config.validate().map_err(Bmp390Error::InvalidConfig)?;
+
+		let mut reg = FIFOWatermark::default();
+		reg.write_watermark_low(config.watermark as u8);
+		reg.write_watermark_high((config.watermark >> 8) as u8);
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+
+		let mut reg = FIFOConfig2::default();
+		reg.write_data_select(config.filtered as u8);
+		reg.write_fifo_subsampling(config.subsampling);
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+
+		let mut reg = FIFOConfig1::default();
+		reg.write_fifo_enable(config.enable);
+		reg.write_fifo_stop_on_full(config.stop_on_full);
+		reg.write_fifo_press_en(config.pressure);
+		reg.write_fifo_temp_en(config.temperature);
+		reg.write_fifo_time_en(config.sensor_time);
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+
+		self.check_errors().await?;
+		self.fifo_config = Some(*config);
+		Ok(())
+	}
+
+	/// The FIFO configuration that is restored after a power-on reset
+	pub fn last_fifo_config(&self) -> Option<&FifoConfig> {
+		self.fifo_config.as_ref()
+	}
+
+	/// Pops pressure and temperature frames from the FIFO into `buffer` until either the
+	/// FIFO is empty or the buffer is full and returns the number of measurements read.
+	///
+	/// Only frames containing both pressure and temperature produce a measurement, all other
+	/// frames are discarded. The sensor only appends its sensor time frame after the fill
+	/// level was read out, so when the FIFO was drained and sensor time frames are enabled
+	/// this reads one more frame to update [`Self::last_fifo_time`]. With
+	/// [`Self::set_auto_recover`] enabled this first checks for a power-on reset.
+	pub async fn read_fifo(&mut self, buffer: &mut [Measurement]) -> Result<usize, Bmp390Error<I::Error>> {
+		self.recover_if_enabled().await?;
+		let length = self.read_register::<FIFOLength>().await.map_err(Bmp390Error::Bus)?;
+		let mut remaining = (length.read_fifo_byte_counter() & 0x1FF) as usize;
+		let mut count = 0;
+
+		while remaining > 0 && count < buffer.len() {
+			let header = self.read_register::<FIFOData>().await.map_err(Bmp390Error::Bus)?.read_fifo_data();
+			remaining -= 1;
+			match header {
+				FRAME_PRESSURE_TEMPERATURE if remaining >= 6 => {
+					let frame = self.read_register::<FIFOPressureTemperature>().await.map_err(Bmp390Error::Bus)?;
+					buffer[count] = self.calibration.compensate(frame.read_pressure(), frame.read_temperature());
+					count += 1;
+					remaining -= 6;
+				}
+				FRAME_SENSOR_TIME if remaining >= 3 => {
+					let frame = self.read_register::<FIFOPayload>().await.map_err(Bmp390Error::Bus)?;
+					self.fifo_time = Some(self.clock.extend_frame(frame.read_payload()));
+					remaining -= 3;
+				}
+				FRAME_TEMPERATURE | FRAME_PRESSURE if remaining >= 3 => {
+					self.read_register::<FIFOPayload>().await.map_err(Bmp390Error::Bus)?;
+					remaining -= 3;
+				}
+				FRAME_CONFIG_CHANGE | FRAME_CONFIG_ERROR if remaining >= 1 => {
+					self.read_register::<FIFOData>().await.map_err(Bmp390Error::Bus)?;
+					remaining -= 1;
+				}
+				// Empty frame or a partially written frame, nothing left to read
+				_ => break,
+			}
+		}
+
+		if remaining == 0 && self.fifo_config.is_some_and(|config| config.sensor_time) {
+			self.read_sensor_time_frame().await?;
+		}
+		Ok(count)
+	}
+
+	/// Reads the sensor time frame that follows the last frame of a drained FIFO
+	async fn read_sensor_time_frame(&mut self) -> Result<(), Bmp390Error<I::Error>> {
+		let header = self.read_register::<FIFOData>().await.map_err(Bmp390Error::Bus)?.read_fifo_data();
+		if header == FRAME_SENSOR_TIME {
+			let frame = self.read_register::<FIFOPayload>().await.map_err(Bmp390Error::Bus)?;
+			self.fifo_time = Some(self.clock.extend_frame(frame.read_payload()));
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_fifo_registers() {
+		let mut reg = FIFOWatermark::default();
+		reg.write_watermark_low(0xF8);
+		reg.write_watermark_high(0x01);
+		assert_eq!(reg.data, [0xF8, 0x01]);
+
+		let mut reg = FIFOConfig1::default();
+		reg.write_fifo_enable(true);
+		reg.write_fifo_press_en(true);
+		reg.write_fifo_temp_en(true);
+		assert_eq!(reg.data[0], 0b0001_1001);
+
+		let mut reg = FIFOConfig2::default();
+		reg.write_data_select(1);
+		reg.write_fifo_subsampling(2);
+		assert_eq!(reg.data[0], 0b0000_1010);
+
+		let config = FifoConfig { subsampling: 8, ..Default::default() };
+		assert_eq!(config.validate(), Err(crate::devices::bmp390::config::InvalidConfig::FifoSubsamplingOutOfRange));
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_read_fifo_sensor_time() {
+		use alloc::vec;
+		use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+		use crate::devices::bmp390::address::Address;
+		use crate::test_utils::block_on;
+
+		let read = |register: u8, data: &[u8]| Transaction::write_read(0x76, vec![register], data.to_vec());
+		let write = |data: &[u8]| Transaction::write(0x76, data.to_vec());
+		let expectations = [
+			write(&[0x15, 0xF8, 0x01]),
+			write(&[0x18, 0x00]),
+			write(&[0x17, 0x1D]),
+			read(0x02, &[0x00]),
+			// One pressure and temperature frame, the sensor time frame follows once drained
+			read(0x12, &[0x07, 0x00]),
+			read(0x14, &[FRAME_PRESSURE_TEMPERATURE]),
+			read(0x14, &[0x00, 0x00, 0x80, 0x00, 0x00, 0x80]),
+			read(0x14, &[FRAME_SENSOR_TIME]),
+			read(0x14, &[0x56, 0x34, 0x12]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let mut bmp390 = BMP390::new_i2c(i2c.clone(), Address::Primary);
+		let mut buffer = [Measurement::default(); 4];
+		block_on(async {
+			bmp390.set_fifo_config(&FifoConfig::default()).await.unwrap();
+			assert_eq!(bmp390.read_fifo(&mut buffer).await.unwrap(), 1);
+		});
+		assert_eq!(bmp390.last_fifo_time(), Some(0x12_3456));
+		i2c.done();
+	}
+}
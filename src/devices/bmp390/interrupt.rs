@@ -0,0 +1,125 @@
+//! Interrupt driven reading of measurements, so that tasks can sleep between samples
+//! instead of polling the device.
+//!
+//! ```ignore
+//! let mut reader = bmp390.interrupt_reader(int_pin).await?;
+//! loop {
+//!     let measurement = reader.next_measurement().await?;
+//!     // ...
+//! }
+//! ```
+use embedded_hal_async::digital::Wait;
+use embedded_registers::RegisterInterfaceAsync as RegisterInterface;
+use crate::common::enums::LogicLevel;
+use crate::devices::bmp390::BMP390;
+use crate::devices::bmp390::compensation::Measurement;
+use crate::devices::bmp390::error::Bmp390Error;
+use crate::devices::bmp390::registers::{InteruptControl, InterruptStatus};
+
+/// Waits on the INT pin of a [`BMP390`] and reads whatever data the interrupt announced.
+///
+/// The interrupt sources and pin polarity have to be configured beforehand, either with
+/// [`BMP390::set_interrupt`] or through [`BMP390::apply_config`].
+pub struct InterruptReader<'a, I: RegisterInterface, P: Wait> {
+	device: &'a mut BMP390<I>,
+	pin: P,
+	active_level: LogicLevel,
+}
+
+impl<I: RegisterInterface> BMP390<I> {
+	/// Creates an [`InterruptReader`] waiting on `pin`, which has to be connected to the INT
+	/// pin of the device. Pending interrupts are cleared so that the first edge is not missed.
+	pub async fn interrupt_reader<P: Wait>(&mut self, pin: P) -> Result<InterruptReader<'_, I, P>, Bmp390Error<I::Error>> {
+		let control = self.read_register::<InteruptControl>().await.map_err(Bmp390Error::Bus)?;
+		self.read_register::<InterruptStatus>().await.map_err(Bmp390Error::Bus)?;
+		Ok(InterruptReader {
+			active_level: control.read_int_active_level(),
+			device: self,
+			pin,
+		})
+	}
+}
+
+impl<I: RegisterInterface, P: Wait> InterruptReader<'_, I, P> {
+	/// Waits for the next interrupt and writes the data it announced into `buffer`.
+	///
+	/// A data ready interrupt yields a single measurement, a FIFO watermark or FIFO full
+	/// interrupt drains the FIFO into `buffer`. Returns the number of measurements written.
+	///
+	/// The pin is waited on for its active level rather than an edge, so an interrupt that
+	/// latched while the previous one was handled is not missed. Reading the status clears a
+	/// latched line again.
+	///
+	/// With [`BMP390::set_auto_recover`] enabled this checks for a power-on reset before
+	/// waiting, which would otherwise have cleared the interrupt configuration.
+	pub async fn next(&mut self, buffer: &mut [Measurement]) -> Result<usize, Bmp390Error<I::Error>> {
+		loop {
+			self.device.recover_if_enabled().await?;
+			match self.active_level {
+				LogicLevel::High => self.pin.wait_for_high().await,
+				LogicLevel::Low => self.pin.wait_for_low().await,
+			}.map_err(|_| Bmp390Error::InterruptPin)?;
+
+			// Reading the status also clears it
+			let status = self.device.read_register::<InterruptStatus>().await.map_err(Bmp390Error::Bus)?;
+			if status.read_fifo_watermark() || status.read_fifo_full() {
+				return self.device.read_fifo(buffer).await;
+			}
+			if status.read_data_ready() {
+				let Some(slot) = buffer.first_mut() else {
+					return Ok(0);
+				};
+				*slot = self.device.read_measurement().await?;
+				return Ok(1);
+			}
+		}
+	}
+
+	/// Waits until the next single measurement is available
+	pub async fn next_measurement(&mut self) -> Result<Measurement, Bmp390Error<I::Error>> {
+		let mut buffer = [Measurement::default()];
+		loop {
+			if self.next(&mut buffer).await? == 1 {
+				return Ok(buffer[0]);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec;
+	use embedded_hal_mock::eh1::digital::{Mock as PinMock, State, Transaction as PinTransaction};
+	use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+	use crate::devices::bmp390::address::Address;
+	use crate::test_utils::block_on;
+	use super::*;
+
+	#[test]
+	fn test_latched_interrupt() {
+		let expectations = [
+			// Active high, a pending status is cleared
+			Transaction::write_read(0x76, vec![0x19], vec![0x06]),
+			Transaction::write_read(0x76, vec![0x11], vec![0x08]),
+			// The line is already high when the reader starts waiting, the status is empty
+			// the first time around
+			Transaction::write_read(0x76, vec![0x11], vec![0x00]),
+			Transaction::write_read(0x76, vec![0x11], vec![0x08]),
+			Transaction::write_read(0x76, vec![0x03], vec![0x20]),
+			Transaction::write_read(0x76, vec![0x04], vec![0x00, 0x00, 0x80, 0x00, 0x00, 0x80]),
+		];
+		let pin_expectations = [
+			PinTransaction::wait_for_state(State::High),
+			PinTransaction::wait_for_state(State::High),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let mut pin = PinMock::new(&pin_expectations);
+		let mut bmp390 = BMP390::new_i2c(i2c.clone(), Address::Primary);
+		block_on(async {
+			let mut reader = bmp390.interrupt_reader(pin.clone()).await.unwrap();
+			reader.next_measurement().await.unwrap();
+		});
+		i2c.done();
+		pin.done();
+	}
+}
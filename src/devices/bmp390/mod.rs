@@ -1,59 +1,194 @@
-use crate::devices::bmp390::registers::{IIRFilterConfiguration, InteruptControl, OutputDataRate, Oversampling};
+use crate::devices::bmp390::compensation::{Calibration, Measurement, TimedMeasurement};
+use crate::devices::bmp390::config::{Bmp390Config, FifoConfig, InterfaceConfig, InterruptConfig};
+use crate::devices::bmp390::error::Bmp390Error;
+use crate::devices::bmp390::presets::Preset;
+use crate::devices::bmp390::variant::Variant;
+use crate::devices::bmp390::sensor_time::SensorClock;
+use crate::devices::bmp390::registers::{BurstRead, BurstReadTime, CalibrationICoefficients, ChipID, Error, Event, IIRFilterConfiguration, InteruptControl, Oversampling, SensorTime, Status};
 use embedded_devices_derive::{device, device_impl};
-use embedded_registers::i2c::I2cDevice;
-use embedded_registers::RegisterInterface;
+#[cfg(feature = "async")]
+use embedded_registers::{i2c::I2cDeviceAsync, spi::SpiDeviceAsync, RegisterInterfaceAsync};
+#[cfg(feature = "sync")]
+use embedded_registers::{i2c::I2cDeviceSync, spi::SpiDeviceSync, RegisterInterfaceSync};
 use address::Address;
 
 pub mod address;
 pub mod registers;
 pub mod enums;
+pub mod config;
+pub mod error;
+pub mod compensation;
+pub mod sensor_time;
+mod recovery;
+pub mod self_test;
+pub mod presets;
+pub mod variant;
+#[cfg(feature = "async")]
+pub mod interrupt;
+mod fifo;
 
+/// Operating pressure range of the sensor in Pascal
+pub const PRESSURE_RANGE: (f32, f32) = (30_000.0, 125_000.0);
+/// Operating temperature range of the sensor in degrees Celsius
+pub const TEMPERATURE_RANGE: (f32, f32) = (-40.0, 85.0);
+
+/// Driver for the BMP390 pressure sensor and the BMP388 and BMP384, which share its register
+/// map. The part is detected by [`Self::init`], see [`Self::variant`]. With the `sync`
+/// feature a blocking flavour of this driver is available as `BMP390Sync`.
 #[device]
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), BMP390(async), RegisterInterface, I2cDevice, SpiDevice),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
 pub struct BMP390<I: RegisterInterface> {
 	interface: I,
+	variant: Option<Variant>,
+	calibration: Calibration,
+	clock: SensorClock,
+	fifo_time: Option<u64>,
+	/// The configuration read in [`Self::init`] with all later changes, re-applied after a
+	/// power-on reset
+	config: Option<Bmp390Config>,
+	/// The last FIFO configuration written, re-applied after a power-on reset
+	fifo_config: Option<FifoConfig>,
+	/// The last serial interface configuration written, re-applied first after a power-on
+	/// reset
+	interface_config: Option<InterfaceConfig>,
+	reset_count: u32,
+	auto_recover: bool,
 }
 
 #[device_impl]
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), BMP390(async), RegisterInterface, I2cDevice, SpiDevice),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
 impl<I: RegisterInterface>BMP390<I> {
 
+	/// Checks the chip id to ensure the device is present and detects which member of the
+	/// family it is, loads the calibration coefficients and makes sure no error is pending.
+	pub async fn init(&mut self) -> Result<(), Bmp390Error<I::Error>> {
+		let chip_id = self.read_register::<ChipID>().await.map_err(Bmp390Error::Bus)?.read_chip_id();
+		let Some(variant) = Variant::from_chip_id(chip_id) else {
+			return Err(Bmp390Error::WrongChipId(chip_id));
+		};
+		self.variant = Some(variant);
+		// Clears the power-on flag so that only later resets are detected
+		self.read_register::<Event>().await.map_err(Bmp390Error::Bus)?;
+		self.load_calibration().await?;
+		self.check_errors().await?;
+		// Track the configuration from here on, so a reset restores it even if it was never
+		// written with `apply_config`
+		self.config = Some(self.get_config().await?);
+		Ok(())
+	}
+
+	/// The part detected by [`Self::init`], `None` before initialization
+	pub fn variant(&self) -> Option<Variant> {
+		self.variant
+	}
+
+	async fn load_calibration(&mut self) -> Result<(), Bmp390Error<I::Error>> {
+		let coefficients = self.read_register::<CalibrationICoefficients>().await.map_err(Bmp390Error::Bus)?;
+		self.calibration = Calibration::from(&coefficients);
+		Ok(())
+	}
+
+	/// Reads the latest pressure and temperature sample and compensates it.
+	///
+	/// With [`Self::set_auto_recover`] enabled this first checks for a power-on reset.
+	pub async fn read_measurement(&mut self) -> Result<Measurement, Bmp390Error<I::Error>> {
+		self.recover_if_enabled().await?;
+		let status = self.read_register::<Status>().await.map_err(Bmp390Error::Bus)?;
+		if !status.read_data_ready_pres() {
+			return Err(Bmp390Error::DataNotReady);
+		}
+		let data = self.read_register::<BurstRead>().await.map_err(Bmp390Error::Bus)?;
+		Ok(self.calibration.compensate(data.read_pressure(), data.read_temperature()))
+	}
 
+	/// Like [`Self::read_measurement`], but also reads the sensor time in the same burst so
+	/// the reading can be placed on the host clock with [`SensorClock::to_host_us`]
+	pub async fn read_timed_measurement(&mut self) -> Result<TimedMeasurement, Bmp390Error<I::Error>> {
+		self.recover_if_enabled().await?;
+		let status = self.read_register::<Status>().await.map_err(Bmp390Error::Bus)?;
+		if !status.read_data_ready_pres() {
+			return Err(Bmp390Error::DataNotReady);
+		}
+		let data = self.read_register::<BurstReadTime>().await.map_err(Bmp390Error::Bus)?;
+		Ok(TimedMeasurement {
+			measurement: self.calibration.compensate(data.read_pressure(), data.read_temperature()),
+			sensor_time: self.clock.extend(data.read_time()),
+		})
+	}
 
-	pub async fn set_power_mode(&mut self, mode: PowerMode, enable_press:bool,enable_temp:bool) -> Result<(), I::Error> {
-		let mut reg = self.read_register::<PowerControl>().await?;
+	/// Reads the `Error` register and reports the most severe flag that is set
+	pub async fn check_errors(&mut self) -> Result<(), Bmp390Error<I::Error>> {
+		let reg = self.read_register::<Error>().await.map_err(Bmp390Error::Bus)?;
+		if reg.read_fatal_err() {
+			Err(Bmp390Error::Fatal)
+		} else if reg.read_cmd_err() {
+			Err(Bmp390Error::Command)
+		} else if reg.read_conf_err() {
+			Err(Bmp390Error::Configuration)
+		} else {
+			Ok(())
+		}
+	}
+
+	pub async fn set_power_mode(&mut self, mode: PowerMode, enable_press:bool,enable_temp:bool) -> Result<(), Bmp390Error<I::Error>> {
+		let mut reg = self.read_register::<PowerControl>().await.map_err(Bmp390Error::Bus)?;
 		reg.write_mode(mode);
 		reg.write_press_en(enable_press);
 		reg.write_temp_en(enable_temp);
-		self.write_register(reg).await?;
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+		if let Some(config) = &mut self.config {
+			config.power_mode = mode;
+			config.enable_press = enable_press;
+			config.enable_temp = enable_temp;
+		}
 		Ok(())
 	}
 
-	pub async fn set_oversample(&mut self, temp_oversample: OversamplingSetting, press_oversample: OversamplingSetting)  -> Result<(), I::Error> {
-		let mut reg = self.read_register::<Oversampling>().await?;
+	pub async fn set_oversample(&mut self, temp_oversample: OversamplingSetting, press_oversample: OversamplingSetting)  -> Result<(), Bmp390Error<I::Error>> {
+		let mut reg = self.read_register::<Oversampling>().await.map_err(Bmp390Error::Bus)?;
 		reg.write_oversampling_temp(temp_oversample);
 		reg.write_over_sampling_press(press_oversample);
-		self.write_register(reg).await?;
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+		if let Some(config) = &mut self.config {
+			config.temp_oversample = temp_oversample;
+			config.press_oversample = press_oversample;
+		}
 		Ok(())
 
 	}
 
-	pub async fn set_output_data_rate(&mut self, output_data_rate: u8)-> Result<(), I::Error> {
-		let mut reg = self.read_register::<OutputDataRate>().await?;
+	pub async fn set_output_data_rate(&mut self, output_data_rate: OutputDataRate)-> Result<(), Bmp390Error<I::Error>> {
+		let mut reg = self.read_register::<registers::OutputDataRate>().await.map_err(Bmp390Error::Bus)?;
 		reg.write_subdivision_factor(output_data_rate);
-		self.write_register(reg).await?;
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+		if let Some(config) = &mut self.config {
+			config.output_data_rate = output_data_rate;
+		}
 		Ok(())
 
 	}
 
-	pub async fn set_iir_filter(&mut self, coef: IIRFilter)-> Result<(), I::Error> {
-		let mut reg = self.read_register::<IIRFilterConfiguration>().await?;
+	pub async fn set_iir_filter(&mut self, coef: IIRFilter)-> Result<(), Bmp390Error<I::Error>> {
+		let mut reg = self.read_register::<IIRFilterConfiguration>().await.map_err(Bmp390Error::Bus)?;
 		reg.write_iir_filter(coef);
 		reg.write_short_in(false);
-		self.write_register(reg).await?;
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+		if let Some(config) = &mut self.config {
+			config.iir_filter = coef;
+		}
 		Ok(())
 	}
 	
 	pub async fn set_interrupt(&mut self, data_ready: bool, fifo_full:bool, fifo_watermark:bool,int_latch:bool,int_active_level:LogicLevel,int_od: InteruptOutput)
-	-> Result<(), I::Error> {
+	-> Result<(), Bmp390Error<I::Error>> {
 		let mut reg = InteruptControl::default();
 		reg.write_data_ready_int(data_ready);
 		reg.write_fifo_full_int(fifo_full);
@@ -61,22 +196,182 @@ impl<I: RegisterInterface>BMP390<I> {
 		reg.write_int_latch(int_latch);
 		reg.write_int_active_level(int_active_level);
 		reg.write_int_od(int_od);
-		self.write_register(reg).await?;
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+		if let Some(config) = &mut self.config {
+			config.interrupt = InterruptConfig {
+				data_ready,
+				fifo_full,
+				fifo_watermark,
+				latch: int_latch,
+				active_level: int_active_level,
+				output: int_od,
+			};
+		}
 		Ok(())
 	}
 
+	/// Validates and writes a complete configuration.
+	///
+	/// The device is put to sleep first so that no measurement runs with a half written
+	/// configuration, the power mode is written last. Afterwards the `Error` register is
+	/// read back to make sure the device accepted the configuration. Accepted configurations
+	/// are remembered and re-applied when a power-on reset is detected.
+	pub async fn apply_config(&mut self, config: &Bmp390Config) -> Result<(), Bmp390Error<I::Error>> {
+		config.validate().map_err(Bmp390Error::InvalidConfig)?;
+
+		let mut power = self.read_register::<PowerControl>().await.map_err(Bmp390Error::Bus)?;
+		power.write_mode(PowerMode::Sleep);
+		self.write_register(power).await.map_err(Bmp390Error::Bus)?;
+
+		let mut reg = Oversampling::default();
+		reg.write_oversampling_temp(config.temp_oversample);
+		reg.write_over_sampling_press(config.press_oversample);
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+
+		let mut reg = registers::OutputDataRate::default();
+		reg.write_subdivision_factor(config.output_data_rate);
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+
+		let mut reg = IIRFilterConfiguration::default();
+		reg.write_iir_filter(config.iir_filter);
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+
+		let int = &config.interrupt;
+		self.set_interrupt(int.data_ready, int.fifo_full, int.fifo_watermark, int.latch, int.active_level, int.output)
+			.await?;
+
+		let mut reg = PowerControl::default();
+		reg.write_mode(config.power_mode);
+		reg.write_press_en(config.enable_press);
+		reg.write_temp_en(config.enable_temp);
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+
+		self.check_errors().await?;
+		self.config = Some(*config);
+		Ok(())
+	}
+
+	/// Applies one of the datasheet recommended [`Preset`]s. For forced mode presets this
+	/// triggers the first measurement, later ones are triggered with [`Self::set_power_mode`].
+	pub async fn apply_preset(&mut self, preset: Preset) -> Result<(), Bmp390Error<I::Error>> {
+		self.apply_config(&preset.config()).await
+	}
+
+	/// Reads the current configuration back from the device
+	pub async fn get_config(&mut self) -> Result<Bmp390Config, Bmp390Error<I::Error>> {
+		let power = self.read_register::<PowerControl>().await.map_err(Bmp390Error::Bus)?;
+		let oversampling = self.read_register::<Oversampling>().await.map_err(Bmp390Error::Bus)?;
+		let odr = self.read_register::<registers::OutputDataRate>().await.map_err(Bmp390Error::Bus)?;
+		let iir = self.read_register::<IIRFilterConfiguration>().await.map_err(Bmp390Error::Bus)?;
+		let int = self.read_register::<InteruptControl>().await.map_err(Bmp390Error::Bus)?;
 
+		Ok(Bmp390Config {
+			power_mode: power.read_mode(),
+			enable_press: power.read_press_en(),
+			enable_temp: power.read_temp_en(),
+			temp_oversample: oversampling.read_oversampling_temp(),
+			press_oversample: oversampling.read_over_sampling_press(),
+			output_data_rate: odr.read_subdivision_factor(),
+			iir_filter: iir.read_iir_filter(),
+			interrupt: InterruptConfig {
+				data_ready: int.read_data_ready_int(),
+				fifo_full: int.read_fifo_full_int(),
+				fifo_watermark: int.read_fifo_watermark_int(),
+				latch: int.read_int_latch(),
+				active_level: int.read_int_active_level(),
+				output: int.read_int_od(),
+			},
+		})
+	}
+
+	/// Reads the sensor time counter and returns it extended to a monotonic tick count,
+	/// see [`SensorClock::extend`]
+	pub async fn read_sensor_time(&mut self) -> Result<u64, Bmp390Error<I::Error>> {
+		let reg = self.read_register::<SensorTime>().await.map_err(Bmp390Error::Bus)?;
+		Ok(self.clock.extend(reg.read_time()))
+	}
+
+	/// Reads the sensor time and correlates it with `host_us`, the current time of the host
+	/// clock in microseconds, to track the drift between both clocks
+	pub async fn sync_sensor_time(&mut self, host_us: u64) -> Result<u64, Bmp390Error<I::Error>> {
+		let ticks = self.read_sensor_time().await?;
+		self.clock.correlate(ticks, host_us);
+		Ok(ticks)
+	}
+
+	/// The clock used to extend and correlate sensor times
+	pub fn sensor_clock(&self) -> &SensorClock {
+		&self.clock
+	}
+
+	/// The sensor time of the last sensor time frame read from the FIFO. The FIFO appends
+	/// this frame once it has been emptied, so it marks the time of the newest measurement
+	/// of the last [`Self::read_fifo`] call.
+	pub fn last_fifo_time(&self) -> Option<u64> {
+		self.fifo_time
+	}
+
+	/// Switches the SPI interface between 4-wire (`false`) and 3-wire (`true`) mode.
+	///
+	/// The register is written without reading it back first, since a device that is
+	/// still in 4-wire mode cannot answer reads on a 3-wire bus. The I2C watchdog bits
+	/// share this register but have no effect while using SPI, they keep the value last
+	/// written by [`Self::set_i2c_watchdog`].
+	pub async fn set_spi_3wire(&mut self, enable: bool) -> Result<(), Bmp390Error<I::Error>> {
+		let config = InterfaceConfig {
+			spi_3wire: enable,
+			..self.interface_config.unwrap_or_default()
+		};
+		self.write_interface_config(&config).await
+	}
+
+	/// Enables or disables the I2C watchdog, which resets the interface when the bus hangs
+	/// in the middle of a transaction for longer than `timeout`. The SPI mode is preserved.
+	pub async fn set_i2c_watchdog(&mut self, enable: bool, timeout: WatchdogTimeout) -> Result<(), Bmp390Error<I::Error>> {
+		let config = InterfaceConfig {
+			i2c_watchdog: enable,
+			i2c_watchdog_timeout: timeout,
+			..self.get_interface_config().await?
+		};
+		self.write_interface_config(&config).await
+	}
+
+	/// Writes the serial interface configuration and remembers it for a power-on reset
+	pub(super) async fn write_interface_config(&mut self, config: &InterfaceConfig) -> Result<(), Bmp390Error<I::Error>> {
+		let mut reg = IFConf::default();
+		reg.write_spi3(config.spi_3wire);
+		reg.write_i2c_wdt_en(config.i2c_watchdog);
+		reg.write_i2c_wdt_sel(config.i2c_watchdog_timeout);
+		self.write_register(reg).await.map_err(Bmp390Error::Bus)?;
+		self.interface_config = Some(*config);
+		Ok(())
+	}
+
+	/// Reads the current serial interface configuration
+	pub async fn get_interface_config(&mut self) -> Result<InterfaceConfig, Bmp390Error<I::Error>> {
+		let reg = self.read_register::<IFConf>().await.map_err(Bmp390Error::Bus)?;
+		Ok(InterfaceConfig {
+			spi_3wire: reg.read_spi3(),
+			i2c_watchdog: reg.read_i2c_wdt_en(),
+			i2c_watchdog_timeout: reg.read_i2c_wdt_sel(),
+		})
+	}
 
 }
 
 type BMP390Codec = embedded_registers::i2c::codecs::OneByteRegAddrCodec;
+/// 7-bit register address, bit 7 set for reads and one dummy byte before read data
+type BMP390SpiCodec = embedded_registers::spi::codecs::SimpleCodec<1, 6, 0, 7, true, 1>;
 
-
-use embedded_hal_async as hal;
 use crate::common::enums::LogicLevel;
-use crate::devices::bmp390::enums::{IIRFilter, InteruptOutput, OversamplingSetting, PowerMode};
-use crate::devices::bmp390::registers::PowerControl;
+use crate::devices::bmp390::enums::{IIRFilter, InteruptOutput, OutputDataRate, OversamplingSetting, PowerMode, WatchdogTimeout};
+use crate::devices::bmp390::registers::{IFConf, PowerControl};
 
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), BMP390(async), RegisterInterface, I2cDevice, SpiDevice),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
 impl<I> BMP390<I2cDevice<I,hal::i2c::SevenBitAddress,BMP390Codec>>
 where I: hal::i2c::I2c<hal::i2c::SevenBitAddress> + hal::i2c::ErrorType{
 	/// Initializes a new device with the given address on the specified bus.
@@ -86,9 +381,73 @@ where I: hal::i2c::I2c<hal::i2c::SevenBitAddress> + hal::i2c::ErrorType{
 	/// initializes the device and ensures that it is working correctly.
 	pub fn new_i2c(interface: I, address: Address) -> Self {
 		Self {
-			interface: I2cDevice::new(interface,address.into(), BMP390Codec::default())
+			interface: I2cDevice::new(interface,address.into()),
+			variant: None,
+			calibration: Calibration::default(),
+			clock: SensorClock::default(),
+			fifo_time: None,
+			config: None,
+			fifo_config: None,
+			interface_config: None,
+			reset_count: 0,
+			auto_recover: false,
 		}
 	}
 
 
-}
\ No newline at end of file
+}
+
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), BMP390(async), RegisterInterface, I2cDevice, SpiDevice),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I> BMP390<SpiDevice<I,BMP390SpiCodec>>
+where I: hal::spi::r#SpiDevice {
+	/// Initializes a new device on the specified SPI device, which is expected to
+	/// handle chip select. This consumes the SPI device `I`.
+	///
+	/// The sensor starts out in 4-wire mode, use [`Self::set_spi_3wire`] if the board
+	/// wires it for 3-wire operation.
+	pub fn new_spi(interface: I) -> Self {
+		Self {
+			interface: SpiDevice::new(interface),
+			variant: None,
+			calibration: Calibration::default(),
+			clock: SensorClock::default(),
+			fifo_time: None,
+			config: None,
+			fifo_config: None,
+			interface_config: None,
+			reset_count: 0,
+			auto_recover: false,
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_interface_register() {
+		let mut reg = IFConf::default();
+		reg.write_spi3(true);
+		reg.write_i2c_wdt_en(true);
+		reg.write_i2c_wdt_sel(WatchdogTimeout::Long);
+		assert_eq!(reg.data[0], 0b0000_0111);
+
+		reg.write_i2c_wdt_sel(WatchdogTimeout::Short);
+		assert_eq!(reg.data[0], 0b0000_0011);
+		assert!(reg.read_spi3());
+	}
+
+	#[test]
+	fn test_burst_read_time() {
+		let reg = BurstReadTime { data: [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0xFF, 0xFF, 0x10, 0x20, 0x30] };
+		assert_eq!(reg.read_pressure(), 0x03_0201);
+		assert_eq!(reg.read_temperature(), 0x06_0504);
+		assert_eq!(reg.read_time(), 0x30_2010);
+	}
+}
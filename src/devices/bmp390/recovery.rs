@@ -0,0 +1,152 @@
+#[cfg(feature = "async")]
+use embedded_registers::RegisterInterfaceAsync;
+#[cfg(feature = "sync")]
+use embedded_registers::RegisterInterfaceSync;
+#[cfg(feature = "async")]
+use crate::devices::bmp390::BMP390;
+#[cfg(feature = "sync")]
+use crate::devices::bmp390::BMP390Sync;
+use crate::devices::bmp390::config::Bmp390Config;
+use crate::devices::bmp390::error::Bmp390Error;
+use crate::devices::bmp390::registers::Event;
+
+#[maybe_async_cfg::maybe(
+	idents(BMP390(async), RegisterInterface),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> BMP390<I> {
+	/// Checks for a power-on reset, for example after a brownout, and restores the device.
+	///
+	/// On a reset the serial interface configuration is written first, so that a device
+	/// wired for 3-wire SPI can answer again. Then the calibration is reloaded, the
+	/// configuration tracked since [`Self::init`] and the last FIFO configuration are
+	/// written again and the sensor clock correlation is dropped. Otherwise the `Error` register is checked. Returns whether a
+	/// reset was detected.
+	pub async fn check_reset(&mut self) -> Result<bool, Bmp390Error<I::Error>> {
+		let event = self.read_register::<Event>().await.map_err(Bmp390Error::Bus)?;
+		if !event.read_por_detected() {
+			self.check_errors().await?;
+			return Ok(false);
+		}
+
+		self.reset_count = self.reset_count.saturating_add(1);
+		self.clock.reset();
+		self.fifo_time = None;
+		if let Some(interface_config) = self.interface_config {
+			self.write_interface_config(&interface_config).await?;
+		}
+		self.load_calibration().await?;
+		if let Some(config) = self.config {
+			self.apply_config(&config).await?;
+		}
+		if let Some(fifo_config) = self.fifo_config {
+			self.set_fifo_config(&fifo_config).await?;
+		}
+		Ok(true)
+	}
+
+	/// When enabled, [`Self::read_measurement`], [`Self::read_timed_measurement`],
+	/// [`Self::read_fifo`] and the interrupt reader call [`Self::check_reset`] before every read
+	pub fn set_auto_recover(&mut self, enable: bool) {
+		self.auto_recover = enable;
+	}
+
+	pub(super) async fn recover_if_enabled(&mut self) -> Result<(), Bmp390Error<I::Error>> {
+		if self.auto_recover {
+			self.check_reset().await?;
+		}
+		Ok(())
+	}
+
+	/// The number of power-on resets detected since the driver was created
+	pub fn reset_count(&self) -> u32 {
+		self.reset_count
+	}
+
+	/// The configuration that is restored after a power-on reset
+	pub fn last_config(&self) -> Option<&Bmp390Config> {
+		self.config.as_ref()
+	}
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+	use alloc::vec;
+	use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+	use crate::devices::bmp390::address::Address;
+	use crate::devices::bmp390::enums::{PowerMode, WatchdogTimeout};
+	use crate::test_utils::block_on;
+	use super::*;
+
+	const CALIBRATION: [u8; 21] = [
+		0xA2, 0x6B, 0x0B, 0x49, 0xF9, 0xFC, 0xFB, 0x60, 0xF3, 0x23, 0x01,
+		0x96, 0x4D, 0xD6, 0x5C, 0x03, 0xF9, 0x93, 0x3E, 0x04, 0xC9,
+	];
+
+	#[test]
+	fn test_power_on_reset() {
+		let read = |register: u8, data: &[u8]| Transaction::write_read(0x76, vec![register], data.to_vec());
+		let write = |register: u8, value: u8| Transaction::write(0x76, vec![register, value]);
+		let expectations = [
+			// init, the configuration is read back from the power-on state
+			read(0x00, &[0x60]),
+			read(0x10, &[0x01]),
+			read(0x31, &CALIBRATION),
+			read(0x02, &[0x00]),
+			read(0x1B, &[0x00]),
+			read(0x1C, &[0x00]),
+			read(0x1D, &[0x00]),
+			read(0x1F, &[0x00]),
+			read(0x19, &[0x02]),
+			// Normal mode without apply_config
+			read(0x1B, &[0x00]),
+			write(0x1B, 0x33),
+			// The device reset before the next read and comes back with normal mode restored
+			read(0x10, &[0x01]),
+			read(0x31, &CALIBRATION),
+			read(0x1B, &[0x00]),
+			write(0x1B, 0x00),
+			write(0x1C, 0x00),
+			write(0x1D, 0x00),
+			write(0x1F, 0x00),
+			write(0x19, 0x02),
+			write(0x1B, 0x33),
+			read(0x02, &[0x00]),
+			read(0x03, &[0x20]),
+			read(0x04, &[0x00, 0x00, 0x80, 0x00, 0x00, 0x80]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let mut bmp390 = BMP390::new_i2c(i2c.clone(), Address::Primary);
+		block_on(async {
+			bmp390.init().await.unwrap();
+			bmp390.set_power_mode(PowerMode::Normal, true, true).await.unwrap();
+			bmp390.set_auto_recover(true);
+			bmp390.read_measurement().await.unwrap();
+		});
+		assert_eq!(bmp390.reset_count(), 1);
+		assert_eq!(bmp390.last_config().map(|config| config.power_mode), Some(PowerMode::Normal));
+		i2c.done();
+	}
+
+	#[test]
+	fn test_interface_config_restored() {
+		let read = |register: u8, data: &[u8]| Transaction::write_read(0x76, vec![register], data.to_vec());
+		let write = |register: u8, value: u8| Transaction::write(0x76, vec![register, value]);
+		let expectations = [
+			read(0x1A, &[0x00]),
+			write(0x1A, 0x06),
+			// The watchdog is restored before anything else after the reset
+			read(0x10, &[0x01]),
+			write(0x1A, 0x06),
+			read(0x31, &CALIBRATION),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let mut bmp390 = BMP390::new_i2c(i2c.clone(), Address::Primary);
+		block_on(async {
+			bmp390.set_i2c_watchdog(true, WatchdogTimeout::Long).await.unwrap();
+			assert!(bmp390.check_reset().await.unwrap());
+		});
+		i2c.done();
+	}
+}
@@ -1,4 +1,4 @@
-use crate::devices::bmp390::enums::{IIRFilter, InteruptOutput, PowerMode, OversamplingSetting};
+use crate::devices::bmp390::enums::{IIRFilter, InteruptOutput, PowerMode, OversamplingSetting, OutputDataRate as DataRate, WatchdogTimeout};
 use bondrewd::BitfieldEnum;
 use embedded_devices_derive::device_register;
 use embedded_registers::register;
@@ -40,7 +40,7 @@ pub struct Error {
 
 /// This register indicates whether a certain type of data is ready to be read
 #[device_register(super::BMP390)]
-#[register(address = 0x02, mode = "r")]
+#[register(address = 0x03, mode = "r")]
 #[bondrewd(read_from = "msb0", default_endianness = "le", enforce_bytes = 1)]
 pub struct Status {
 	#[bondrewd(bit_length = 1, reserve)]
@@ -86,6 +86,22 @@ pub struct BurstRead {
 }
 
 
+/// The measurement data followed by the sensor time, read in one burst so the time
+/// belongs to the same conversion
+#[device_register(super::BMP390)]
+#[register(address = 0x04, mode = "r")]
+#[bondrewd(read_from = "msb0", default_endianness = "le", enforce_bytes = 11)]
+pub struct BurstReadTime {
+	#[bondrewd(bit_length = 24)]
+	pub pressure: u32,
+	#[bondrewd(bit_length = 24)]
+	pub temperature: u32,
+	#[bondrewd(bit_length = 16, reserve)]
+	reserve: u16,
+	#[bondrewd(bit_length = 24)]
+	pub time: u32
+}
+
 /// This register contains the sensor time reading
 #[device_register(super::BMP390)]
 #[register(address = 0x0C, mode = "r")]
@@ -142,15 +158,38 @@ pub struct FIFOData {
 }
 
 
+/// A pressure and temperature frame popped from the FIFO, temperature comes first
+#[device_register(super::BMP390)]
+#[register(address = 0x14, mode = "r")]
+#[bondrewd(read_from = "msb0", default_endianness = "le", enforce_bytes = 6)]
+pub struct FIFOPressureTemperature {
+	#[bondrewd(bit_length = 24)]
+	pub temperature: u32,
+	#[bondrewd(bit_length = 24)]
+	pub pressure: u32
+}
+
+/// The payload of a single sensor or sensor time frame popped from the FIFO
+#[device_register(super::BMP390)]
+#[register(address = 0x14, mode = "r")]
+#[bondrewd(read_from = "msb0", default_endianness = "le", enforce_bytes = 3)]
+pub struct FIFOPayload {
+	#[bondrewd(bit_length = 24)]
+	pub payload: u32
+}
+
 #[device_register(super::BMP390)]
 #[register(address = 0x15, mode = "rw")]
 #[bondrewd(read_from = "msb0", default_endianness = "le", enforce_bytes = 2)]
 pub struct FIFOWatermark {
-	#[bondrewd(bit_length = 9)]
-	pub watermark: u16,
+	/// Bits 0 to 7 of the 9-bit watermark
+	pub watermark_low: u8,
 	#[bondrewd(bit_length = 7, reserve)]
 	#[allow(dead_code)]
 	pub reserved: u8,
+	/// Bit 8 of the watermark
+	#[bondrewd(bit_length = 1)]
+	pub watermark_high: u8,
 }
 
 #[device_register(super::BMP390)]
@@ -214,9 +253,13 @@ pub struct InteruptControl {
 pub struct IFConf {
 	#[bondrewd(bit_length = 5, reserve)]
 	reserve: u8,
-	i2c_wdt_sel: bool,
-	i2c_wdt_en: bool,
-	spi3: bool
+	/// Timeout of the I2C watchdog
+	#[bondrewd(bit_length = 1,enum_primitive = "u8")]
+	pub i2c_wdt_sel: WatchdogTimeout,
+	/// Enable the I2C watchdog, which resets the interface when a transaction stalls
+	pub i2c_wdt_en: bool,
+	/// Enable 3-wire SPI mode
+	pub spi3: bool
 }
 
 #[device_register(super::BMP390)]
@@ -253,10 +296,9 @@ pub struct Oversampling {
 pub struct OutputDataRate {
 	#[bondrewd(bit_length = 3, reserve)]
 	reserve: u8,
-	/// The subdivision factor = 2^value range: 0-17
-	/// See datasheet for more nfo
-	#[bondrewd(bit_length = 5)]
-	pub subdivision_factor: u8,
+	/// The subdivision factor, the output data rate is 200Hz / 2^value
+	#[bondrewd(bit_length = 5,enum_primitive = "u8")]
+	pub subdivision_factor: DataRate,
 }
 
 #[device_register(super::BMP390)]
@@ -0,0 +1,239 @@
+#[cfg(feature = "async")]
+use embedded_registers::RegisterInterfaceAsync;
+#[cfg(feature = "sync")]
+use embedded_registers::RegisterInterfaceSync;
+#[cfg(feature = "async")]
+use crate::devices::bmp390::BMP390;
+#[cfg(feature = "sync")]
+use crate::devices::bmp390::BMP390Sync;
+use crate::devices::bmp390::{PRESSURE_RANGE, TEMPERATURE_RANGE};
+use crate::devices::bmp390::compensation::{Calibration, Measurement};
+use crate::devices::bmp390::enums::PowerMode;
+use crate::devices::bmp390::error::Bmp390Error;
+use crate::devices::bmp390::registers::{BurstRead, CalibrationICoefficients, ChipID, PowerControl, Revision, Status};
+use crate::devices::bmp390::variant::Variant;
+
+/// Number of forced mode measurements taken by [`BMP390::self_test`]
+const SELF_TEST_SAMPLES: u8 = 8;
+/// Interval at which the status is polled while waiting for a forced measurement
+const POLL_INTERVAL_MS: u32 = 5;
+/// A forced measurement takes at most 130ms with 32x oversampling of both sensors
+const MEASUREMENT_TIMEOUT_MS: u32 = 200;
+
+/// Largest plausible spread of consecutive pressure readings of a resting sensor in Pascal
+const MAX_PRESSURE_SPREAD: f32 = 10.0;
+/// Largest plausible spread of consecutive temperature readings in degrees Celsius
+const MAX_TEMPERATURE_SPREAD: f32 = 0.5;
+
+/// The outcome of [`BMP390::self_test`], meant to be sent back as telemetry
+#[derive(Clone, Copy, Default, PartialEq, Debug, defmt::Format)]
+pub struct SelfTestReport {
+	pub chip_id: u8,
+	pub revision: u8,
+	/// The chip id belongs to one of the supported [`Variant`]s
+	pub chip_id_ok: bool,
+	pub revision_ok: bool,
+	/// The calibration coefficients are neither all 0x00 nor all 0xFF
+	pub calibration_ok: bool,
+	/// Number of forced mode measurements taken
+	pub samples: u8,
+	/// Mean pressure in Pascal
+	pub pressure_mean: f32,
+	/// Difference between the highest and lowest pressure reading in Pascal
+	pub pressure_spread: f32,
+	/// Mean temperature in degrees Celsius
+	pub temperature_mean: f32,
+	/// Difference between the highest and lowest temperature reading in degrees Celsius
+	pub temperature_spread: f32,
+	pressure_min: f32,
+	pressure_max: f32,
+	temperature_min: f32,
+	temperature_max: f32,
+}
+
+impl SelfTestReport {
+	fn new(chip_id: u8, revision: u8, calibration: &[u8]) -> Self {
+		let variant = Variant::from_chip_id(chip_id);
+		Self {
+			chip_id,
+			revision,
+			chip_id_ok: variant.is_some(),
+			revision_ok: variant.is_some_and(|variant| variant.revision() == revision),
+			calibration_ok: !calibration.iter().all(|&b| b == 0x00) && !calibration.iter().all(|&b| b == 0xFF),
+			pressure_min: f32::MAX,
+			pressure_max: f32::MIN,
+			temperature_min: f32::MAX,
+			temperature_max: f32::MIN,
+			..Default::default()
+		}
+	}
+
+	fn add_sample(&mut self, measurement: &Measurement) {
+		let n = self.samples as f32;
+		self.pressure_mean = (self.pressure_mean * n + measurement.pressure) / (n + 1.0);
+		self.temperature_mean = (self.temperature_mean * n + measurement.temperature) / (n + 1.0);
+		self.pressure_min = self.pressure_min.min(measurement.pressure);
+		self.pressure_max = self.pressure_max.max(measurement.pressure);
+		self.temperature_min = self.temperature_min.min(measurement.temperature);
+		self.temperature_max = self.temperature_max.max(measurement.temperature);
+		self.pressure_spread = self.pressure_max - self.pressure_min;
+		self.temperature_spread = self.temperature_max - self.temperature_min;
+		self.samples += 1;
+	}
+
+	/// All readings lie within the operating range of the sensor
+	pub fn range_ok(&self) -> bool {
+		self.samples > 0
+			&& self.pressure_min >= PRESSURE_RANGE.0 && self.pressure_max <= PRESSURE_RANGE.1
+			&& self.temperature_min >= TEMPERATURE_RANGE.0 && self.temperature_max <= TEMPERATURE_RANGE.1
+	}
+
+	/// The readings of the resting sensor did not scatter more than expected
+	pub fn noise_ok(&self) -> bool {
+		self.samples > 0
+			&& self.pressure_spread <= MAX_PRESSURE_SPREAD
+			&& self.temperature_spread <= MAX_TEMPERATURE_SPREAD
+	}
+
+	/// The go/no-go verdict, every individual check passed
+	pub fn passed(&self) -> bool {
+		self.chip_id_ok && self.revision_ok && self.calibration_ok && self.range_ok() && self.noise_ok()
+	}
+}
+
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), BMP390(async), RegisterInterface),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> BMP390<I> {
+	/// Runs a go/no-go check of the sensor.
+	///
+	/// This checks chip id, revision and calibration coefficients and then takes a series of
+	/// forced mode measurements with the current oversampling settings to confirm that they
+	/// are physically plausible and not too noisy. The sensor has to rest during the test.
+	/// The power mode is restored afterwards, also when a bus error or measurement timeout
+	/// aborts the test. Everything else is reported in the returned [`SelfTestReport`].
+	pub async fn self_test<D: hal::delay::DelayNs>(&mut self, delay: &mut D) -> Result<SelfTestReport, Bmp390Error<I::Error>> {
+		let chip_id = self.read_register::<ChipID>().await.map_err(Bmp390Error::Bus)?.read_chip_id();
+		let revision = self.read_register::<Revision>().await.map_err(Bmp390Error::Bus)?.read_rev_id();
+		let coefficients = self.read_register::<CalibrationICoefficients>().await.map_err(Bmp390Error::Bus)?;
+		let mut report = SelfTestReport::new(chip_id, revision, &coefficients.data);
+		if !report.chip_id_ok || !report.calibration_ok {
+			return Ok(report);
+		}
+		let calibration = Calibration::from(&coefficients);
+
+		let previous = self.read_register::<PowerControl>().await.map_err(Bmp390Error::Bus)?;
+		let result = self.take_self_test_samples(&calibration, &mut report, delay).await;
+		// Restore the power mode on every exit path, a failed measurement takes precedence
+		let restored = self.write_register(previous).await.map_err(Bmp390Error::Bus);
+		result.and(restored).map(|_| report)
+	}
+
+	/// Takes the forced mode measurements of [`Self::self_test`], leaving the device in
+	/// whichever power mode it ended up in
+	async fn take_self_test_samples<D: hal::delay::DelayNs>(&mut self, calibration: &Calibration, report: &mut SelfTestReport, delay: &mut D) -> Result<(), Bmp390Error<I::Error>> {
+		let mut power = PowerControl::default();
+		power.write_mode(PowerMode::Sleep);
+		self.write_register(power).await.map_err(Bmp390Error::Bus)?;
+
+		power.write_press_en(true);
+		power.write_temp_en(true);
+		power.write_mode(PowerMode::Forced);
+		for _ in 0..SELF_TEST_SAMPLES {
+			self.write_register(power).await.map_err(Bmp390Error::Bus)?;
+
+			let mut waited = 0;
+			while !self.read_register::<Status>().await.map_err(Bmp390Error::Bus)?.read_data_ready_pres() {
+				if waited >= MEASUREMENT_TIMEOUT_MS {
+					return Err(Bmp390Error::Timeout);
+				}
+				delay.delay_ms(POLL_INTERVAL_MS).await;
+				waited += POLL_INTERVAL_MS;
+			}
+
+			let data = self.read_register::<BurstRead>().await.map_err(Bmp390Error::Bus)?;
+			report.add_sample(&calibration.compensate(data.read_pressure(), data.read_temperature()));
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn measurement(pressure: f32, temperature: f32) -> Measurement {
+		Measurement { pressure, temperature }
+	}
+
+	#[test]
+	fn test_calibration_check() {
+		assert!(!SelfTestReport::new(0x60, 0x01, &[0x00; 21]).calibration_ok);
+		assert!(!SelfTestReport::new(0x60, 0x01, &[0xFF; 21]).calibration_ok);
+		assert!(SelfTestReport::new(0x60, 0x01, &[0x12; 21]).calibration_ok);
+	}
+
+	#[test]
+	fn test_chip_id_check() {
+		assert!(SelfTestReport::new(0x50, 0x01, &[0x12; 21]).chip_id_ok);
+		assert!(!SelfTestReport::new(0x58, 0x01, &[0x12; 21]).chip_id_ok);
+		assert!(!SelfTestReport::new(0x60, 0x02, &[0x12; 21]).revision_ok);
+	}
+
+	#[test]
+	fn test_plausible_readings() {
+		let mut report = SelfTestReport::new(0x60, 0x01, &[0x12; 21]);
+		assert!(!report.passed());
+		for i in 0..8 {
+			report.add_sample(&measurement(101_325.0 + i as f32 * 0.5, 21.0));
+		}
+		assert!(report.passed());
+		assert_eq!(report.pressure_spread, 3.5);
+		assert_eq!(report.pressure_mean, 101_326.75);
+	}
+
+	#[test]
+	fn test_implausible_readings() {
+		let mut report = SelfTestReport::new(0x60, 0x01, &[0x12; 21]);
+		report.add_sample(&measurement(0.0, 21.0));
+		assert!(!report.range_ok());
+
+		let mut report = SelfTestReport::new(0x60, 0x01, &[0x12; 21]);
+		report.add_sample(&measurement(101_325.0, 21.0));
+		report.add_sample(&measurement(101_425.0, 21.0));
+		assert!(report.range_ok());
+		assert!(!report.noise_ok());
+		assert!(!report.passed());
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_restore_after_timeout() {
+		use alloc::vec;
+		use embedded_hal_mock::eh1::delay::NoopDelay;
+		use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+		use crate::devices::bmp390::address::Address;
+		use crate::test_utils::block_on;
+
+		let mut expectations = vec![
+			Transaction::write_read(0x76, vec![0x00], vec![0x60]),
+			Transaction::write_read(0x76, vec![0x01], vec![0x01]),
+			Transaction::write_read(0x76, vec![0x31], vec![0x12; 21]),
+			// Normal mode with both sensors enabled
+			Transaction::write_read(0x76, vec![0x1B], vec![0x33]),
+			Transaction::write(0x76, vec![0x1B, 0x00]),
+			Transaction::write(0x76, vec![0x1B, 0x13]),
+		];
+		// The measurement never finishes
+		for _ in 0..=MEASUREMENT_TIMEOUT_MS / POLL_INTERVAL_MS {
+			expectations.push(Transaction::write_read(0x76, vec![0x03], vec![0x00]));
+		}
+		expectations.push(Transaction::write(0x76, vec![0x1B, 0x33]));
+		let mut i2c = Mock::new(&expectations);
+		let mut bmp390 = BMP390::new_i2c(i2c.clone(), Address::Primary);
+		assert_eq!(block_on(bmp390.self_test(&mut NoopDelay)), Err(Bmp390Error::Timeout));
+		i2c.done();
+	}
+}
@@ -0,0 +1,180 @@
+//! Decoding of the 24-bit sensor time counter and correlation with a host clock.
+//!
+//! The counter wraps roughly every 11 minutes (2^24 ticks), so register reads passed to
+//! [`SensorClock::extend`] have to be less than that apart. FIFO frames passed to
+//! [`SensorClock::extend_frame`] may be older than the newest read, which halves the limit
+//! to roughly 5.5 minutes (2^23 ticks) between a frame and the newest value.
+
+/// Duration of one sensor time tick is 39.0625us (25.6kHz), expressed as a fraction
+const TICK_US_NUMERATOR: u64 = 625;
+const TICK_US_DENOMINATOR: u64 = 16;
+
+/// The sensor time counter is 24 bits wide
+const COUNTER_BITS: u32 = 24;
+const COUNTER_MASK: u32 = (1 << COUNTER_BITS) - 1;
+const HALF_RANGE: u32 = 1 << (COUNTER_BITS - 1);
+
+/// Extends the wrapping sensor time counter into 64-bit ticks and estimates the drift of
+/// the sensor oscillator against a host clock.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, defmt::Format)]
+pub struct SensorClock {
+	last_raw: Option<u32>,
+	wraps: u64,
+	/// First correlated (ticks, host microseconds) pair
+	anchor: Option<(u64, u64)>,
+	/// Most recent correlated (ticks, host microseconds) pair
+	latest: Option<(u64, u64)>,
+}
+
+impl SensorClock {
+	/// Converts a raw 24-bit counter value read from the sensor time register into a
+	/// monotonically increasing tick count.
+	///
+	/// Register reads only move forward, so any backwards jump counts as a wrap. Consecutive
+	/// reads must be less than 2^24 ticks apart.
+	pub fn extend(&mut self, raw: u32) -> u64 {
+		let raw = raw & COUNTER_MASK;
+		if let Some(last) = self.last_raw {
+			if raw < last { self.wraps += 1; }
+		}
+		self.last_raw = Some(raw);
+		(self.wraps << COUNTER_BITS) | raw as u64
+	}
+
+	/// Converts a raw 24-bit counter value from a FIFO sensor time frame into a tick count.
+	///
+	/// A frame may be older than the last register read, so a backwards jump of up to half
+	/// the counter range is extended relative to the newest value instead of counting as a
+	/// wrap. The frame must be less than 2^23 ticks away from the newest value.
+	pub fn extend_frame(&mut self, raw: u32) -> u64 {
+		let raw = raw & COUNTER_MASK;
+		let Some(last) = self.last_raw else {
+			return self.extend(raw);
+		};
+
+		if last.wrapping_sub(raw) & COUNTER_MASK <= HALF_RANGE {
+			// At most half the range older than the newest value, possibly before its wrap
+			let wraps = if raw > last { self.wraps.saturating_sub(1) } else { self.wraps };
+			(wraps << COUNTER_BITS) | raw as u64
+		} else {
+			// Newer than the newest value, possibly after the next wrap
+			self.extend(raw)
+		}
+	}
+
+	/// Converts ticks into microseconds using the nominal tick duration
+	pub const fn ticks_to_us(ticks: u64) -> u64 {
+		ticks * TICK_US_NUMERATOR / TICK_US_DENOMINATOR
+	}
+
+	/// Records that the sensor time `ticks` was read at `host_us` on the host clock.
+	///
+	/// The first pair becomes the anchor of the correlation, later pairs refine the
+	/// estimated rate of the sensor clock relative to the host.
+	pub fn correlate(&mut self, ticks: u64, host_us: u64) {
+		if self.anchor.is_none() {
+			self.anchor = Some((ticks, host_us));
+		} else {
+			self.latest = Some((ticks, host_us));
+		}
+	}
+
+	/// Forgets all correlation data, for example after the sensor was reset
+	pub fn reset(&mut self) {
+		*self = Self::default();
+	}
+
+	/// Elapsed sensor and host microseconds between the anchor and the latest pair
+	fn spans(&self) -> Option<(u64, u64)> {
+		let (anchor_ticks, anchor_host) = self.anchor?;
+		let (latest_ticks, latest_host) = self.latest?;
+		let sensor_span = Self::ticks_to_us(latest_ticks.checked_sub(anchor_ticks)?);
+		let host_span = latest_host.checked_sub(anchor_host)?;
+		(sensor_span > 0 && host_span > 0).then_some((sensor_span, host_span))
+	}
+
+	/// The drift of the sensor clock in parts per million, positive if the sensor runs fast
+	pub fn drift_ppm(&self) -> Option<i64> {
+		let (sensor_span, host_span) = self.spans()?;
+		let ppm = (sensor_span as i128 - host_span as i128) * 1_000_000 / host_span as i128;
+		Some(ppm as i64)
+	}
+
+	/// Maps a sensor time onto the host clock in microseconds.
+	///
+	/// Until a second pair was correlated the nominal tick duration is assumed, afterwards
+	/// the measured rate is used. Returns `None` before anything was correlated.
+	pub fn to_host_us(&self, ticks: u64) -> Option<u64> {
+		let (anchor_ticks, anchor_host) = self.anchor?;
+		let offset_us = (Self::ticks_to_us(ticks) as i128) - (Self::ticks_to_us(anchor_ticks) as i128);
+		let offset_us = match self.spans() {
+			Some((sensor_span, host_span)) => offset_us * host_span as i128 / sensor_span as i128,
+			None => offset_us,
+		};
+		u64::try_from(anchor_host as i128 + offset_us).ok()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_wraparound() {
+		let mut clock = SensorClock::default();
+		assert_eq!(clock.extend(0xFF_FFF0), 0xFF_FFF0);
+		assert_eq!(clock.extend(0x00_0010), 0x100_0010);
+		assert_eq!(clock.extend(0x00_0020), 0x100_0020);
+		assert_eq!(clock.extend(0x70_0000), 0x170_0000);
+		assert_eq!(clock.extend(0xE0_0000), 0x1E0_0000);
+		assert_eq!(clock.extend(0x00_0005), 0x200_0005);
+	}
+
+	#[test]
+	fn test_gap_over_half_range() {
+		// Reads more than half the range apart still detect the next wrap
+		let mut clock = SensorClock::default();
+		assert_eq!(clock.extend(0x00_0010), 0x00_0010);
+		assert_eq!(clock.extend(0x80_0020), 0x80_0020);
+		assert_eq!(clock.extend(0x00_0005), 0x100_0005);
+	}
+
+	#[test]
+	fn test_out_of_order_frames() {
+		let mut clock = SensorClock::default();
+		assert_eq!(clock.extend(0x00_1000), 0x00_1000);
+		// A slightly older frame does not count as a wrap
+		assert_eq!(clock.extend_frame(0x00_0F00), 0x00_0F00);
+		assert_eq!(clock.extend(0x00_1100), 0x00_1100);
+
+		// Around a wrap, a frame from before it stays in the previous range
+		let mut clock = SensorClock::default();
+		assert_eq!(clock.extend(0xFF_FF00), 0xFF_FF00);
+		assert_eq!(clock.extend(0x00_0010), 0x100_0010);
+		assert_eq!(clock.extend_frame(0xFF_FFF0), 0xFF_FFF0);
+		assert_eq!(clock.extend(0x00_0020), 0x100_0020);
+		assert_eq!(clock.extend_frame(0x00_0005), 0x100_0005);
+
+		// A frame newer than the last read moves the clock forward
+		assert_eq!(clock.extend_frame(0x00_0100), 0x100_0100);
+	}
+
+	#[test]
+	fn test_ticks_to_us() {
+		assert_eq!(SensorClock::ticks_to_us(16), 625);
+		assert_eq!(SensorClock::ticks_to_us(25_600), 1_000_000);
+	}
+
+	#[test]
+	fn test_drift_correlation() {
+		let mut clock = SensorClock::default();
+		assert_eq!(clock.to_host_us(0), None);
+
+		// The sensor counts 1002.5ms while the host counts 1000ms, so it runs 2500ppm fast
+		clock.correlate(0, 5_000_000);
+		assert_eq!(clock.to_host_us(25_600), Some(6_000_000));
+		clock.correlate(25_664, 6_000_000);
+		assert_eq!(clock.drift_ppm(), Some(2500));
+		assert_eq!(clock.to_host_us(25_664 * 2), Some(7_000_000));
+	}
+}
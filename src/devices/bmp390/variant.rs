@@ -0,0 +1,60 @@
+/// The members of the BMP3 family handled by the [`super::BMP390`] driver. They share the
+/// register map and differ mainly in noise performance and chip id.
+///
+/// All of them have the same 512 byte FIFO with the same configuration registers and
+/// frame format, so [`super::BMP390::set_fifo_config`] and [`super::BMP390::read_fifo`] do
+/// not depend on the variant. See [`Self::fifo_size`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum Variant {
+	/// BMP390, chip id 0x60
+	Bmp390,
+	/// BMP388 or BMP384, chip id 0x50. Both parts report the same chip id and revision,
+	/// so they cannot be told apart over the bus.
+	Bmp388,
+}
+
+impl Variant {
+	/// Identifies the part from the value of the `ChipID` register
+	pub fn from_chip_id(chip_id: u8) -> Option<Self> {
+		match chip_id {
+			0x60 => Some(Variant::Bmp390),
+			0x50 => Some(Variant::Bmp388),
+			_ => None,
+		}
+	}
+
+	/// The value of the `ChipID` register
+	pub fn chip_id(self) -> u8 {
+		match self {
+			Variant::Bmp390 => 0x60,
+			Variant::Bmp388 => 0x50,
+		}
+	}
+
+	/// The expected value of the `Revision` register
+	pub fn revision(self) -> u8 {
+		match self {
+			Variant::Bmp390 | Variant::Bmp388 => 0x01,
+		}
+	}
+
+	/// Size of the FIFO in bytes, the largest usable watermark is one less
+	pub fn fifo_size(self) -> u16 {
+		match self {
+			Variant::Bmp390 | Variant::Bmp388 => 512,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_chip_id() {
+		for variant in [Variant::Bmp390, Variant::Bmp388] {
+			assert_eq!(Variant::from_chip_id(variant.chip_id()), Some(variant));
+		}
+		assert_eq!(Variant::from_chip_id(0x58), None);
+	}
+}
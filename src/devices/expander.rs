@@ -0,0 +1,149 @@
+//! Register logic shared by the IO expanders built around input, output, polarity inversion
+//! and configuration registers: the 8-bit parts driven by [`super::pca9557`] and the 16-bit
+//! parts driven by [`super::pca9555`].
+//!
+//! Raw register values are handled as `u16` in which bit n belongs to pin n, the 8-bit parts
+//! only use the lower byte. Both drivers implement `ExpanderPort` for their register
+//! access and share the shadow and pin logic through its provided methods.
+use bondrewd::BitfieldEnum;
+use crate::common::enums::LogicLevel;
+
+/// Power-on values of the writable registers of a part
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub struct PowerOn {
+	pub output: u16,
+	pub polarity: u16,
+	pub configuration: u16,
+}
+
+/// The writable registers of an expander, each holding one bit per pin
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub(crate) enum PortRegister {
+	Output,
+	Polarity,
+	Configuration,
+}
+
+impl PortRegister {
+	pub(crate) const ALL: [PortRegister; 3] = [PortRegister::Output, PortRegister::Polarity, PortRegister::Configuration];
+}
+
+/// The register access of an expander driver with shadow copies of its writable registers.
+///
+/// A driver implements the raw register access and the shadow storage, the pin logic is
+/// shared through the provided methods. Values are `u16` in which bit n belongs to pin n.
+#[allow(async_fn_in_trait)]
+#[maybe_async_cfg::maybe(
+	idents(ExpanderPort(async)),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+pub(crate) trait ExpanderPort {
+	type Error;
+	/// Number of pins of the part
+	const PINS: u8;
+
+	/// Reads the input registers
+	async fn read_input(&mut self) -> Result<u16, Self::Error>;
+
+	/// Reads `register` from the device without touching its shadow
+	async fn read_port(&mut self, register: PortRegister) -> Result<u16, Self::Error>;
+
+	/// Writes `register` of the device without touching its shadow
+	async fn write_port(&mut self, register: PortRegister, value: u16) -> Result<(), Self::Error>;
+
+	/// The value last written to or read from `register`
+	fn shadow(&self, register: PortRegister) -> u16;
+
+	fn set_shadow(&mut self, register: PortRegister, value: u16);
+
+	/// Bit of pin `n`.
+	///
+	/// # Panics
+	/// If the part has no pin `n`.
+	fn pin_bit(n: u8) -> u16 {
+		assert!(n < Self::PINS, "pin index out of range");
+		1 << n
+	}
+
+	/// Reads `register` and updates its shadow
+	async fn read_shadowed(&mut self, register: PortRegister) -> Result<u16, Self::Error> {
+		let value = self.read_port(register).await?;
+		self.set_shadow(register, value);
+		Ok(value)
+	}
+
+	/// Writes `register` if `value` differs from its shadow
+	async fn write_shadowed(&mut self, register: PortRegister, value: u16) -> Result<(), Self::Error> {
+		if value != self.shadow(register) {
+			self.write_port(register, value).await?;
+			self.set_shadow(register, value);
+		}
+		Ok(())
+	}
+
+	/// Reads all writable registers back into their shadows
+	async fn resync_shadows(&mut self) -> Result<(), Self::Error> {
+		for register in PortRegister::ALL {
+			self.read_shadowed(register).await?;
+		}
+		Ok(())
+	}
+
+	/// Reads the input level of pin `n`
+	async fn read_input_pin(&mut self, n: u8) -> Result<LogicLevel, Self::Error> {
+		Ok((self.read_input().await? & Self::pin_bit(n) != 0).into())
+	}
+
+	/// Drives the outputs selected by `mask` to the bits of `value`, the other outputs keep
+	/// their level
+	async fn write_output_masked(&mut self, mask: u16, value: u16) -> Result<(), Self::Error> {
+		let output = masked(self.shadow(PortRegister::Output), mask, value);
+		self.write_shadowed(PortRegister::Output, output).await
+	}
+
+	/// Drives pin `n` to `level`
+	async fn write_output_pin(&mut self, n: u8, level: LogicLevel) -> Result<(), Self::Error> {
+		let value = if level.into() { 0xFFFF } else { 0x0000 };
+		self.write_output_masked(Self::pin_bit(n), value).await
+	}
+
+	/// Inverts the output level of pin `n`
+	async fn toggle_output_pin(&mut self, n: u8) -> Result<(), Self::Error> {
+		let output = self.shadow(PortRegister::Output) ^ Self::pin_bit(n);
+		self.write_shadowed(PortRegister::Output, output).await
+	}
+}
+
+/// Unpacks a raw register value into per pin values, index n is pin n
+pub(crate) fn unpack<T: BitfieldEnum<Primitive = u8>, const N: usize>(raw: u16) -> [T; N] {
+	core::array::from_fn(|n| T::from_primitive(((raw >> n) & 1) as u8))
+}
+
+/// Packs per pin values into a raw register value, index n is pin n
+pub(crate) fn pack<T: BitfieldEnum<Primitive = u8>, const N: usize>(pins: [T; N]) -> u16 {
+	pins.into_iter()
+		.enumerate()
+		.fold(0, |raw, (n, pin)| raw | (((pin.into_primitive() & 1) as u16) << n))
+}
+
+/// Replaces the bits of `current` selected by `mask` with those of `value`
+pub(crate) fn masked(current: u16, mask: u16, value: u16) -> u16 {
+	(current & !mask) | (value & mask)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::common::enums::LogicLevel;
+
+	#[test]
+	fn test_pack_unpack() {
+		let pins: [LogicLevel; 16] = unpack(0x8001);
+		assert_eq!(pins[0], LogicLevel::High);
+		assert_eq!(pins[1], LogicLevel::Low);
+		assert_eq!(pins[15], LogicLevel::High);
+		assert_eq!(pack(pins), 0x8001);
+		assert_eq!(masked(0x00FF, 0x0F0F, 0xF0F0), 0x00F0);
+	}
+}
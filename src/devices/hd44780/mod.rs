@@ -0,0 +1,386 @@
+//! This module contains a driver for [`HD44780`] compatible character LCDs in 4-bit mode,
+//! connected through any [`port::OutputPort`], for example a
+//! [`crate::devices::pca9557::PCA9557`] IO expander.
+//!
+//! Every byte goes out as one call to the port carrying both nibbles with their enable
+//! pulses, which a PCA9557 sends as a single I2C write. [`Hd44780::init`] makes the used
+//! pins outputs through [`port::OutputPort::enable_outputs`]. The RW pin has to be tied low
+//! or driven low, the driver never reads the busy flag and waits the execution times
+//! instead.
+//!
+//! ```ignore
+//! // IO0 of the PCA9557 is open-drain and can not drive the signals high
+//! let pins = LcdPins { rs: 1, rw: None, e: 2, data: [4, 5, 6, 7], backlight: Some(3) };
+//! let mut lcd = Hd44780::new(expander, delay, pins, 16, 2);
+//! // Switches IO1 to IO7 of the expander to outputs
+//! lcd.init().await?;
+//! lcd.write_str("Hello").await?;
+//! ```
+//!
+//! [`HD44780`]: https://www.sparkfun.com/datasheets/LCD/HD44780.pdf
+//!
+pub mod port;
+
+#[cfg(feature = "async")]
+use crate::devices::hd44780::port::OutputPort;
+#[cfg(feature = "sync")]
+use crate::devices::hd44780::port::OutputPortSync;
+
+/// Power-up time of the controller before the first write
+const POWER_ON_US: u32 = 50_000;
+/// Execution time of most commands and data writes
+const EXECUTION_US: u32 = 50;
+/// Execution time of clear and return home
+const CLEAR_US: u32 = 2_000;
+
+const CLEAR_DISPLAY: u8 = 0x01;
+const RETURN_HOME: u8 = 0x02;
+/// Cursor moves right, the display does not shift
+const ENTRY_MODE: u8 = 0x06;
+const DISPLAY_CONTROL: u8 = 0x08;
+const DISPLAY_ON: u8 = 0x04;
+const CURSOR_ON: u8 = 0x02;
+const BLINK_ON: u8 = 0x01;
+/// 4-bit interface with 5x8 dot characters
+const FUNCTION_SET: u8 = 0x20;
+const TWO_LINES: u8 = 0x08;
+const SET_CGRAM_ADDRESS: u8 = 0x40;
+const SET_DDRAM_ADDRESS: u8 = 0x80;
+
+/// Outputs of the port the LCD signals are connected to
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub struct LcdPins {
+	pub rs: u8,
+	/// Driven low if connected
+	pub rw: Option<u8>,
+	pub e: u8,
+	/// D4 to D7
+	pub data: [u8; 4],
+	/// Switches the backlight on while high
+	pub backlight: Option<u8>,
+}
+
+impl LcdPins {
+	/// Outputs of all connected signals
+	fn outputs(&self) -> impl Iterator<Item = u8> {
+		let optional = self.rw.into_iter().chain(self.backlight);
+		self.data.into_iter().chain([self.rs, self.e]).chain(optional)
+	}
+
+	/// Whether all outputs are below 8 and none is used twice
+	pub fn is_valid(&self) -> bool {
+		let mut used = 0u8;
+		self.outputs().all(|n| {
+			let free = n < 8 && used & (1 << n) == 0;
+			used |= 1 << (n & 7);
+			free
+		})
+	}
+
+	/// All outputs used by the LCD
+	pub fn mask(&self) -> u8 {
+		self.outputs().fold(0, |mask, n| mask | 1 << n)
+	}
+
+	/// Port value putting `nibble` on D4 to D7
+	fn nibble(&self, nibble: u8) -> u8 {
+		self.data
+			.iter()
+			.enumerate()
+			.filter(|(bit, _)| nibble & (1 << bit) != 0)
+			.fold(0, |value, (_, &n)| value | 1 << n)
+	}
+}
+
+/// Driver for an HD44780 character LCD with `cols` columns and up to four `rows`. With the
+/// `sync` feature a blocking flavour of this driver is available as `Hd44780Sync`.
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), Hd44780(async), OutputPort(async)),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+pub struct Hd44780<P: OutputPort, D: hal::delay::DelayNs> {
+	port: P,
+	delay: D,
+	pins: LcdPins,
+	cols: u8,
+	rows: u8,
+	/// Flags of the last display control command
+	display_control: u8,
+	backlight: bool,
+}
+
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), Hd44780(async), OutputPort(async)),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<P: OutputPort, D: hal::delay::DelayNs> Hd44780<P, D> {
+	/// Creates the driver without touching the port, call [`Self::init`] before use.
+	///
+	/// # Panics
+	/// If `rows` is 0 or larger than 4, or `pins` is not [`LcdPins::is_valid`].
+	pub fn new(port: P, delay: D, pins: LcdPins, cols: u8, rows: u8) -> Self {
+		assert!((1..=4).contains(&rows), "unsupported number of rows");
+		assert!(pins.is_valid(), "LCD outputs out of range or used twice");
+		Self {
+			port,
+			delay,
+			pins,
+			cols,
+			rows,
+			display_control: DISPLAY_ON,
+			backlight: true,
+		}
+	}
+
+	/// Returns the port and the delay
+	pub fn release(self) -> (P, D) {
+		(self.port, self.delay)
+	}
+
+	/// Enables the outputs of the port, switches the controller to 4-bit mode from any state,
+	/// clears the display and turns it on without cursor
+	pub async fn init(&mut self) -> Result<(), P::Error> {
+		self.port.enable_outputs(self.pins.mask()).await?;
+		self.delay.delay_us(POWER_ON_US).await;
+		// Three times 8-bit mode, as the controller might be in the middle of a 4-bit transfer
+		for wait_us in [4_500, 150, 150] {
+			self.write_nibble(0x3).await?;
+			self.delay.delay_us(wait_us).await;
+		}
+		self.write_nibble(0x2).await?;
+		self.delay.delay_us(EXECUTION_US).await;
+
+		let lines = if self.rows > 1 { TWO_LINES } else { 0 };
+		self.command(FUNCTION_SET | lines).await?;
+		self.command(DISPLAY_CONTROL).await?;
+		self.clear().await?;
+		self.command(ENTRY_MODE).await?;
+		self.command(DISPLAY_CONTROL | self.display_control).await
+	}
+
+	/// Clears the display and moves the cursor to the first column of the first row
+	pub async fn clear(&mut self) -> Result<(), P::Error> {
+		self.command(CLEAR_DISPLAY).await?;
+		self.delay.delay_us(CLEAR_US).await;
+		Ok(())
+	}
+
+	/// Moves the cursor to the first column of the first row
+	pub async fn home(&mut self) -> Result<(), P::Error> {
+		self.command(RETURN_HOME).await?;
+		self.delay.delay_us(CLEAR_US).await;
+		Ok(())
+	}
+
+	/// Moves the cursor to `col` of `row`, both counted from 0.
+	///
+	/// # Panics
+	/// If `col` or `row` is not below the number of columns or rows.
+	pub async fn set_cursor(&mut self, col: u8, row: u8) -> Result<(), P::Error> {
+		assert!(col < self.cols, "column out of range");
+		assert!(row < self.rows, "row out of range");
+		// Rows 2 and 3 continue rows 0 and 1 in the display RAM
+		let offset = [0x00, 0x40, self.cols, 0x40 + self.cols][row as usize];
+		self.command(SET_DDRAM_ADDRESS | (offset + col)).await
+	}
+
+	pub async fn set_display(&mut self, on: bool) -> Result<(), P::Error> {
+		self.set_display_flag(DISPLAY_ON, on).await
+	}
+
+	/// Shows the cursor as underline
+	pub async fn set_cursor_visible(&mut self, on: bool) -> Result<(), P::Error> {
+		self.set_display_flag(CURSOR_ON, on).await
+	}
+
+	/// Blinks the character at the cursor
+	pub async fn set_blink(&mut self, on: bool) -> Result<(), P::Error> {
+		self.set_display_flag(BLINK_ON, on).await
+	}
+
+	/// Switches the backlight, does nothing if it has no pin
+	pub async fn set_backlight(&mut self, on: bool) -> Result<(), P::Error> {
+		self.backlight = on;
+		match self.pins.backlight {
+			Some(n) => self.port.write_masked(1 << n, &[(on as u8) << n]).await,
+			None => Ok(()),
+		}
+	}
+
+	/// Defines the custom character `location` (0 to 7) from 8 rows of 5 pixels, the lowest
+	/// bit is the rightmost pixel. Call [`Self::set_cursor`] afterwards, this leaves the
+	/// address counter in the character generator RAM.
+	///
+	/// # Panics
+	/// If `location` is larger than 7.
+	pub async fn create_char(&mut self, location: u8, bitmap: [u8; 8]) -> Result<(), P::Error> {
+		assert!(location < 8, "custom character location out of range");
+		self.command(SET_CGRAM_ADDRESS | location << 3).await?;
+		for row in bitmap {
+			self.write_byte(row & 0x1F).await?;
+		}
+		Ok(())
+	}
+
+	/// Writes a character code at the cursor, 0 to 7 are the custom characters
+	pub async fn write_byte(&mut self, byte: u8) -> Result<(), P::Error> {
+		self.send(true, byte).await
+	}
+
+	/// Writes `text` at the cursor, characters outside of ASCII are shown as `?`
+	pub async fn write_str(&mut self, text: &str) -> Result<(), P::Error> {
+		for c in text.chars() {
+			self.write_byte(if c.is_ascii() { c as u8 } else { b'?' }).await?;
+		}
+		Ok(())
+	}
+
+	pub async fn command(&mut self, command: u8) -> Result<(), P::Error> {
+		self.send(false, command).await
+	}
+
+	async fn set_display_flag(&mut self, flag: u8, on: bool) -> Result<(), P::Error> {
+		if on {
+			self.display_control |= flag;
+		} else {
+			self.display_control &= !flag;
+		}
+		self.command(DISPLAY_CONTROL | self.display_control).await
+	}
+
+	/// Port value with RS low, RW low and the backlight
+	fn base(&self) -> u8 {
+		match self.pins.backlight {
+			Some(n) if self.backlight => 1 << n,
+			_ => 0,
+		}
+	}
+
+	/// Sends a single nibble with RS low, only used during initialization
+	async fn write_nibble(&mut self, nibble: u8) -> Result<(), P::Error> {
+		let value = self.base() | self.pins.nibble(nibble);
+		let e = 1 << self.pins.e;
+		self.port.write_masked(self.pins.mask(), &[value | e, value]).await
+	}
+
+	/// Sends both nibbles of a byte in one port access, the LCD latches each on the falling
+	/// edge of E
+	async fn send(&mut self, rs: bool, byte: u8) -> Result<(), P::Error> {
+		let base = self.base() | (rs as u8) << self.pins.rs;
+		let (high, low) = (base | self.pins.nibble(byte >> 4), base | self.pins.nibble(byte & 0x0F));
+		let e = 1 << self.pins.e;
+		self.port.write_masked(self.pins.mask(), &[high | e, high, low | e, low]).await?;
+		self.delay.delay_us(EXECUTION_US).await;
+		Ok(())
+	}
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+	use super::*;
+	use alloc::vec::Vec;
+	use embedded_hal_mock::eh1::delay::NoopDelay;
+	use crate::test_utils::block_on;
+
+	// IO0 is left out since it is open-drain on the PCA9557
+	const PINS: LcdPins = LcdPins { rs: 1, rw: None, e: 2, data: [4, 5, 6, 7], backlight: Some(3) };
+
+	/// Records the nibbles latched by the falling edges of E as (RS, nibble)
+	#[derive(Default)]
+	struct Latch {
+		value: u8,
+		outputs: u8,
+		latched: Vec<(bool, u8)>,
+	}
+
+	impl OutputPort for Latch {
+		type Error = ();
+
+		async fn write_masked(&mut self, mask: u8, values: &[u8]) -> Result<(), ()> {
+			assert_eq!(mask, 0xFE);
+			for &value in values {
+				if self.value & 0x04 != 0 && value & 0x04 == 0 {
+					self.latched.push((value & 0x02 != 0, value >> 4));
+				}
+				self.value = value;
+			}
+			Ok(())
+		}
+
+		async fn enable_outputs(&mut self, mask: u8) -> Result<(), ()> {
+			self.outputs |= mask;
+			Ok(())
+		}
+	}
+
+	/// Joins the latched nibbles after the 4-bit switch into bytes
+	fn bytes(latched: &[(bool, u8)]) -> Vec<(bool, u8)> {
+		latched.chunks(2).map(|pair| (pair[0].0, pair[0].1 << 4 | pair[1].1)).collect()
+	}
+
+	#[test]
+	fn test_init_sequence() {
+		let mut lcd = Hd44780::new(Latch::default(), NoopDelay, PINS, 20, 4);
+		block_on(lcd.init()).unwrap();
+		let (port, _) = lcd.release();
+		assert_eq!(port.latched[..4], [(false, 0x3), (false, 0x3), (false, 0x3), (false, 0x2)]);
+		assert_eq!(bytes(&port.latched[4..]), [(false, 0x28), (false, 0x08), (false, 0x01), (false, 0x06), (false, 0x0C)]);
+		assert_eq!(port.value & 0x08, 0x08, "backlight is on");
+		assert_eq!(port.outputs, 0xFE);
+	}
+
+	#[test]
+	fn test_pin_validation() {
+		assert!(PINS.is_valid());
+		assert!(!LcdPins { e: 8, ..PINS }.is_valid());
+		assert!(!LcdPins { backlight: Some(4), ..PINS }.is_valid());
+	}
+
+	#[test]
+	#[should_panic(expected = "column out of range")]
+	fn test_cursor_column() {
+		let mut lcd = Hd44780::new(Latch::default(), NoopDelay, PINS, 16, 2);
+		let _ = block_on(lcd.set_cursor(16, 0));
+	}
+
+	#[test]
+	fn test_text_and_cursor() {
+		let mut lcd = Hd44780::new(Latch::default(), NoopDelay, PINS, 20, 4);
+		block_on(async {
+			lcd.set_cursor(2, 3).await.unwrap();
+			lcd.write_str("Hi°").await.unwrap();
+			lcd.create_char(1, [0xFF; 8]).await.unwrap();
+			lcd.set_blink(true).await.unwrap();
+		});
+		let (port, _) = lcd.release();
+		let bytes = bytes(&port.latched);
+		assert_eq!(bytes[..4], [(false, 0x80 | 0x56), (true, b'H'), (true, b'i'), (true, b'?')]);
+		assert_eq!(bytes[4], (false, 0x48));
+		assert_eq!(bytes[5..13], [(true, 0x1F); 8]);
+		assert_eq!(bytes[13], (false, 0x0D));
+	}
+
+	#[test]
+	fn test_pca9557_backend() {
+		use alloc::vec;
+		use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+		use crate::devices::pca9557::{enums::Address, PCA9557};
+
+		// IO1 to IO7 become outputs, then 'A' (0x41) goes out in one write while RS and the
+		// backlight stay high and E pulses for each nibble
+		let expectations = [
+			Transaction::write(0x18, vec![0x03, 0x01]),
+			Transaction::write(0x18, vec![0x01, 0x4E, 0x4A, 0x1E, 0x1A]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let mut expander = PCA9557::new_i2c(i2c.clone(), Address::Primary).unwrap();
+		block_on(async {
+			expander.enable_outputs(PINS.mask()).await.unwrap();
+			let mut lcd = Hd44780::new(expander, NoopDelay, PINS, 16, 2);
+			lcd.write_byte(b'A').await.unwrap();
+		});
+		i2c.done();
+	}
+}
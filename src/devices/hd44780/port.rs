@@ -0,0 +1,27 @@
+/// A group of up to 8 outputs that can be driven together, bit n belongs to output n. With
+/// the `sync` feature a blocking flavour is available as `OutputPortSync`.
+///
+/// Implemented by [`crate::devices::pca9557::PCA9557`] and by arrays of its output pin
+/// handles, where bit n belongs to the pin at index n of the array.
+#[allow(async_fn_in_trait)]
+#[maybe_async_cfg::maybe(
+	idents(OutputPort(async)),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+pub trait OutputPort {
+	type Error;
+
+	/// Drives the outputs selected by `mask` to each of `values` in turn, the other outputs
+	/// keep their level. Each value has to stay on the outputs for at least 1 µs, which any
+	/// I2C expander does by itself. Ports able to send all values in one bus transaction
+	/// should do so.
+	async fn write_masked(&mut self, mask: u8, values: &[u8]) -> Result<(), Self::Error>;
+
+	/// Makes the outputs selected by `mask` drive their pins, for ports whose outputs start
+	/// out as inputs. Does nothing by default.
+	async fn enable_outputs(&mut self, mask: u8) -> Result<(), Self::Error> {
+		let _ = mask;
+		Ok(())
+	}
+}
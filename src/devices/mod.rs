@@ -1,5 +1,8 @@
 //! This module contains driver code for the various devices. It is *not* intended for
 //! application specific implementation but rather serve as an interface for the hardware
 
+pub mod expander;
 pub mod pca9557;
-pub mod bmp390;
\ No newline at end of file
+pub mod pca9555;
+pub mod bmp390;
+pub mod hd44780;
\ No newline at end of file
@@ -0,0 +1,73 @@
+use defmt::Format;
+use crate::common::enums::LogicLevel;
+use crate::devices::pca9555::model::Model;
+use crate::devices::pca9557::enums::InvalidAddress;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Format)]
+pub enum Address {
+	/// Primary device address 0x20 (A0, A1, A2 connected to GND).
+	Primary,
+	/// Address selected by the levels of the A0, A1 and A2 pins, see [`Address::from_pins`].
+	Pins(LogicLevel, LogicLevel, LogicLevel),
+	/// Raw address checked against the range of the parts, created by [`Address::custom`].
+	Custom(CustomAddress),
+}
+
+/// A raw address that lies in the address range of the parts
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Format)]
+pub struct CustomAddress(u8);
+
+impl CustomAddress {
+	pub fn get(self) -> u8 {
+		self.0
+	}
+}
+
+impl Address {
+	/// Address selected by strapping the A0, A1 and A2 pins to the given levels
+	pub fn from_pins(a0: LogicLevel, a1: LogicLevel, a2: LogicLevel) -> Self {
+		Address::Pins(a0, a1, a2)
+	}
+
+	/// Checks a raw address against the address range shared by all parts of [`Model`]
+	pub fn custom(address: u8) -> Result<Self, InvalidAddress> {
+		if Model::default().address_range().contains(&address) {
+			Ok(Address::Custom(CustomAddress(address)))
+		} else {
+			Err(InvalidAddress(address))
+		}
+	}
+}
+
+impl From<Address> for u8 {
+	fn from(address: Address) -> Self {
+		let base = *Model::default().address_range().start();
+		match address {
+			Address::Primary => base,
+			Address::Pins(a0, a1, a2) => base | (bool::from(a2) as u8) << 2 | (bool::from(a1) as u8) << 1 | bool::from(a0) as u8,
+			Address::Custom(address) => address.get(),
+		}
+	}
+}
+
+impl TryFrom<u8> for Address {
+	type Error = InvalidAddress;
+
+	fn try_from(address: u8) -> Result<Self, Self::Error> {
+		Self::custom(address)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use LogicLevel::{High, Low};
+
+	#[test]
+	fn test_pin_addresses() {
+		assert_eq!(u8::from(Address::Primary), 0x20);
+		assert_eq!(u8::from(Address::from_pins(High, High, Low)), 0x23);
+		assert_eq!(Address::custom(0x27).map(u8::from), Ok(0x27));
+		assert_eq!(Address::try_from(0x28), Err(InvalidAddress(0x28)));
+	}
+}
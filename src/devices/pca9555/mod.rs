@@ -0,0 +1,273 @@
+//! This module contains a driver for the [`PCA9555`] 16-bit I2C IO expander IC and the other
+//! parts with the same register map, listed in [`model::Model`].
+//!
+//! The pins are numbered 0 to 15, pin n is IO0_n for n below 8 and IO1_(n-8) above.
+//!
+//! [`PCA9555`]: https://www.ti.com/lit/ds/symlink/pca9555.pdf
+//!
+mod registers;
+mod address;
+pub mod model;
+
+// This serves to export the enums used by the driver
+pub mod enums {
+	pub use crate::devices::pca9557::enums::{Polarity, IODirection, InvalidAddress};
+	pub use super::address::{Address, CustomAddress};
+}
+
+use crate::common::enums::LogicLevel;
+use crate::devices::expander::{pack, unpack, PortRegister};
+#[cfg(feature = "async")]
+use crate::devices::expander::ExpanderPort;
+#[cfg(feature = "sync")]
+use crate::devices::expander::ExpanderPortSync;
+use crate::devices::pca9555::address::Address;
+use crate::devices::pca9555::model::Model;
+use crate::devices::pca9555::registers::{Configuration, InputPort, OutputPort, PolarityInversion};
+use crate::devices::pca9557::enums::{IODirection, Polarity};
+use embedded_devices_derive::{device, device_impl};
+#[cfg(feature = "async")]
+use embedded_registers::{i2c::I2cDeviceAsync, RegisterInterfaceAsync};
+#[cfg(feature = "sync")]
+use embedded_registers::{i2c::I2cDeviceSync, RegisterInterfaceSync};
+
+/// Driver for the PCA9555 IO expander and the other parts of [`Model`]. With the `sync`
+/// feature a blocking flavour of this driver is available as `PCA9555Sync`.
+///
+/// Like [`crate::devices::pca9557::PCA9557`] the driver keeps shadow copies of the writable
+/// registers and writes them only when they change, both ports are always written together.
+#[device]
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), PCA9555(async), RegisterInterface, I2cDevice),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+pub struct PCA9555<I: RegisterInterface> {
+	interface: I,
+	model: Model,
+	/// Shadow of the `OutputPort` registers
+	output: u16,
+	/// Shadow of the `PolarityInversion` registers
+	polarity: u16,
+	/// Shadow of the `Configuration` registers
+	configuration: u16,
+}
+
+#[device_impl]
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), PCA9555(async), ExpanderPort(async), RegisterInterface, I2cDevice),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> PCA9555<I> {
+	/// The part this driver was created for
+	pub fn model(&self) -> Model {
+		self.model
+	}
+
+	pub async fn get_input_port(&mut self) -> Result<[LogicLevel;16], I::Error> {
+		Ok(unpack(self.read_input_raw().await?))
+	}
+
+	pub async fn get_output_port(&mut self) -> Result<[LogicLevel;16], I::Error> {
+		Ok(unpack(self.read_shadowed(PortRegister::Output).await?))
+	}
+
+	pub async fn get_polarity(&mut self) -> Result<[Polarity;16], I::Error> {
+		Ok(unpack(self.read_shadowed(PortRegister::Polarity).await?))
+	}
+
+	pub async fn get_configuration(&mut self) -> Result<[IODirection;16], I::Error> {
+		Ok(unpack(self.read_shadowed(PortRegister::Configuration).await?))
+	}
+
+	pub async fn set_output_port(&mut self, input: [LogicLevel;16]) -> Result<(), I::Error> {
+		self.write_output_raw(pack(input)).await
+	}
+
+	pub async fn set_polarity(&mut self, input: [Polarity;16]) -> Result<(), I::Error> {
+		self.write_polarity_raw(pack(input)).await
+	}
+
+	pub async fn set_configuration(&mut self, input: [IODirection;16]) -> Result<(), I::Error> {
+		self.write_configuration_raw(pack(input)).await
+	}
+
+	/// Reads both `InputPort` registers, bit n holds the level of pin n
+	pub async fn read_input_raw(&mut self) -> Result<u16, I::Error> {
+		Ok(u16::from_le_bytes(self.read_register::<InputPort>().await?.data))
+	}
+
+	/// The value last written to the `OutputPort` registers, bit n drives pin n
+	pub fn output_raw(&self) -> u16 {
+		self.output
+	}
+
+	/// Writes the `OutputPort` registers if `value` differs from their shadow
+	pub async fn write_output_raw(&mut self, value: u16) -> Result<(), I::Error> {
+		self.write_shadowed(PortRegister::Output, value).await
+	}
+
+	/// Writes the `PolarityInversion` registers if `value` differs from their shadow, a set
+	/// bit n inverts the input pin n
+	pub async fn write_polarity_raw(&mut self, value: u16) -> Result<(), I::Error> {
+		self.write_shadowed(PortRegister::Polarity, value).await
+	}
+
+	/// Writes the `Configuration` registers if `value` differs from their shadow, a set bit n
+	/// makes pin n an input
+	pub async fn write_configuration_raw(&mut self, value: u16) -> Result<(), I::Error> {
+		self.write_shadowed(PortRegister::Configuration, value).await
+	}
+
+	/// Reads the writable registers back into their shadows
+	pub async fn resync(&mut self) -> Result<(), I::Error> {
+		self.resync_shadows().await
+	}
+
+	/// Reads the input level of pin `n`.
+	///
+	/// # Panics
+	/// If `n` is larger than 15.
+	pub async fn read_pin(&mut self, n: u8) -> Result<LogicLevel, I::Error> {
+		self.read_input_pin(n).await
+	}
+
+	/// Drives pin `n` to `level`, the other outputs keep their level.
+	///
+	/// # Panics
+	/// If `n` is larger than 15.
+	pub async fn set_pin(&mut self, n: u8, level: LogicLevel) -> Result<(), I::Error> {
+		self.write_output_pin(n, level).await
+	}
+
+	/// Inverts the output level of pin `n`.
+	///
+	/// # Panics
+	/// If `n` is larger than 15.
+	pub async fn toggle_pin(&mut self, n: u8) -> Result<(), I::Error> {
+		self.toggle_output_pin(n).await
+	}
+
+	/// Drives the outputs selected by the set bits of `mask` to the corresponding bits of
+	/// `value` in a single write, the other outputs keep their level
+	pub async fn set_pins_masked(&mut self, mask: u16, value: u16) -> Result<(), I::Error> {
+		self.write_output_masked(mask, value).await
+	}
+}
+
+#[maybe_async_cfg::maybe(
+	idents(PCA9555(async), ExpanderPort(async), RegisterInterface),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> ExpanderPort for PCA9555<I> {
+	type Error = I::Error;
+	const PINS: u8 = 16;
+
+	async fn read_input(&mut self) -> Result<u16, I::Error> {
+		self.read_input_raw().await
+	}
+
+	async fn read_port(&mut self, register: PortRegister) -> Result<u16, I::Error> {
+		let data = match register {
+			PortRegister::Output => self.read_register::<OutputPort>().await?.data,
+			PortRegister::Polarity => self.read_register::<PolarityInversion>().await?.data,
+			PortRegister::Configuration => self.read_register::<Configuration>().await?.data,
+		};
+		Ok(u16::from_le_bytes(data))
+	}
+
+	async fn write_port(&mut self, register: PortRegister, value: u16) -> Result<(), I::Error> {
+		let data = value.to_le_bytes();
+		match register {
+			PortRegister::Output => self.write_register(OutputPort { data }).await,
+			PortRegister::Polarity => self.write_register(PolarityInversion { data }).await,
+			PortRegister::Configuration => self.write_register(Configuration { data }).await,
+		}
+	}
+
+	fn shadow(&self, register: PortRegister) -> u16 {
+		match register {
+			PortRegister::Output => self.output,
+			PortRegister::Polarity => self.polarity,
+			PortRegister::Configuration => self.configuration,
+		}
+	}
+
+	fn set_shadow(&mut self, register: PortRegister, value: u16) {
+		match register {
+			PortRegister::Output => self.output = value,
+			PortRegister::Polarity => self.polarity = value,
+			PortRegister::Configuration => self.configuration = value,
+		}
+	}
+}
+
+type PCA9555Codec = embedded_registers::i2c::codecs::OneByteRegAddrCodec;
+
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), PCA9555(async), RegisterInterface, I2cDevice),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I> PCA9555<I2cDevice<I,hal::i2c::SevenBitAddress,PCA9555Codec>>
+where I: hal::i2c::I2c<hal::i2c::SevenBitAddress> + hal::i2c::ErrorType{
+	/// Initializes a new device with the given address on the specified bus.
+	/// This consumes the I2C bus `I`.
+	///
+	/// The shadow registers assume the device is in its power-on state, call
+	/// [`Self::resync`] if it may have been configured before.
+	pub fn new_i2c(interface: I, address: Address) -> Self {
+		Self::new_i2c_model(interface, address, Model::default())
+	}
+
+	/// Like [`Self::new_i2c`] for the other part with the same register map
+	pub fn new_i2c_model(interface: I, address: Address, model: Model) -> Self {
+		let power_on = model.power_on();
+		Self {
+			interface: I2cDevice::new(interface,address.into()),
+			model,
+			output: power_on.output,
+			polarity: power_on.polarity,
+			configuration: power_on.configuration,
+		}
+	}
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_port_round_trip() {
+		use alloc::vec;
+		use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+		use crate::test_utils::block_on;
+
+		// IO0_0 and IO1_7 are set, port 0 is transferred first
+		let expectations = [
+			Transaction::write_read(0x20, vec![0x00], vec![0x01, 0x80]),
+			Transaction::write(0x20, vec![0x02, 0x01, 0x80]),
+			Transaction::write(0x20, vec![0x02, 0x01, 0x81]),
+			Transaction::write(0x20, vec![0x06, 0xFE, 0x7F]),
+			Transaction::write_read(0x20, vec![0x06], vec![0xFE, 0x7F]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let mut expander = PCA9555::new_i2c_model(i2c.clone(), Address::Primary, Model::TCA9535);
+		let mut levels = [LogicLevel::Low; 16];
+		levels[0] = LogicLevel::High;
+		levels[15] = LogicLevel::High;
+		let mut directions = [IODirection::Input; 16];
+		directions[0] = IODirection::Output;
+		directions[15] = IODirection::Output;
+		block_on(async {
+			assert_eq!(expander.get_input_port().await.unwrap(), levels);
+			expander.set_output_port(levels).await.unwrap();
+			expander.set_pin(8, LogicLevel::High).await.unwrap();
+			expander.set_configuration(directions).await.unwrap();
+			assert_eq!(expander.get_configuration().await.unwrap(), directions);
+		});
+		i2c.done();
+	}
+}
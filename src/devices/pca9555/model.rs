@@ -0,0 +1,33 @@
+use core::ops::RangeInclusive;
+use crate::devices::expander::PowerOn;
+
+/// The 16-bit parts sharing the register map of the PCA9555
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, defmt::Format)]
+pub enum Model {
+	/// Has internal pull-up resistors on all pins
+	#[default]
+	PCA9555,
+	/// PCA9555 without the internal pull-up resistors
+	TCA9535,
+}
+
+impl Model {
+	/// The I2C addresses selectable with the A0 to A2 pins
+	pub fn address_range(self) -> RangeInclusive<u8> {
+		0x20..=0x27
+	}
+
+	pub fn power_on(self) -> PowerOn {
+		PowerOn { output: 0xFFFF, polarity: 0x0000, configuration: 0xFFFF }
+	}
+
+	/// Whether the part has an interrupt output signalling input changes
+	pub fn has_interrupt(self) -> bool {
+		true
+	}
+
+	/// Whether the pins are pulled up internally
+	pub fn has_pull_ups(self) -> bool {
+		self == Model::PCA9555
+	}
+}
@@ -0,0 +1,41 @@
+use embedded_devices_derive::device_register;
+use embedded_registers::register;
+
+// The registers come in pairs, one per port, which are read and written together. Port 0
+// holds IO0_0 to IO0_7 in the first byte, port 1 holds IO1_0 to IO1_7 in the second byte.
+
+/// This register reflects the incoming logic level of pins, regardless of configuration
+#[device_register(super::PCA9555)]
+#[register(address = 0x00, mode = "r")]
+#[bondrewd(read_from = "msb0", default_endianness = "le", enforce_bytes = 2)]
+pub struct InputPort {
+	pub port0: u8,
+	pub port1: u8,
+}
+
+/// This register shows the outgoing logic levels of the pins defined as outputs
+#[device_register(super::PCA9555)]
+#[register(address = 0x02, mode = "rw")]
+#[bondrewd(read_from = "msb0", default_endianness = "le", enforce_bytes = 2)]
+pub struct OutputPort {
+	pub port0: u8,
+	pub port1: u8,
+}
+
+/// A set bit inverts the polarity of the corresponding input
+#[device_register(super::PCA9555)]
+#[register(address = 0x04, mode = "rw")]
+#[bondrewd(read_from = "msb0", default_endianness = "le", enforce_bytes = 2)]
+pub struct PolarityInversion {
+	pub port0: u8,
+	pub port1: u8,
+}
+
+/// A set bit configures the corresponding pin as input
+#[device_register(super::PCA9555)]
+#[register(address = 0x06, mode = "rw")]
+#[bondrewd(read_from = "msb0", default_endianness = "le", enforce_bytes = 2)]
+pub struct Configuration {
+	pub port0: u8,
+	pub port1: u8,
+}
@@ -1,19 +1,96 @@
 use defmt::Format;
-
-const PRIMARY_ADDRESS: u8 = 0x24;
+use crate::common::enums::LogicLevel;
+use crate::devices::pca9557::model::Model;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Format)]
 pub enum Address {
-	/// Primary device address 0x24 (A0, A1, A2 connected to GND).
+	/// Primary device address (A0, A1, A2 connected to GND), 0x18 for the PCA9557.
 	Primary,
-	Custom(u8),
+	/// Address selected by the levels of the A0, A1 and A2 pins, see [`Address::from_pins`].
+	Pins(LogicLevel, LogicLevel, LogicLevel),
+	/// Raw address checked against the range of the part, created by [`Address::custom`] or
+	/// [`Address::custom_for`].
+	Custom(CustomAddress),
 }
 
-impl From<Address> for u8 {
-	fn from(address: Address) -> Self {
-		match address {
-			Address::Primary => PRIMARY_ADDRESS,
-			Address::Custom(x) => x,
+/// A raw address that lies in the address range of the part it was checked for
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Format)]
+pub struct CustomAddress(u8);
+
+impl CustomAddress {
+	pub fn get(self) -> u8 {
+		self.0
+	}
+}
+
+/// The address is not selectable on the part
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Format)]
+pub struct InvalidAddress(pub u8);
+
+impl Address {
+	/// Address selected by strapping the A0, A1 and A2 pins to the given levels
+	pub fn from_pins(a0: LogicLevel, a1: LogicLevel, a2: LogicLevel) -> Self {
+		Address::Pins(a0, a1, a2)
+	}
+
+	/// Checks a raw address against the address range of the PCA9557
+	pub fn custom(address: u8) -> Result<Self, InvalidAddress> {
+		Self::custom_for(Model::PCA9557, address)
+	}
+
+	/// Checks a raw address against the address range of `model`
+	pub fn custom_for(model: Model, address: u8) -> Result<Self, InvalidAddress> {
+		if model.address_range().contains(&address) {
+			Ok(Address::Custom(CustomAddress(address)))
+		} else {
+			Err(InvalidAddress(address))
+		}
+	}
+
+	/// The 7-bit I2C address on a part of type `model`. Fails for a custom address that was
+	/// checked for a model with a different address range.
+	pub fn resolve(self, model: Model) -> Result<u8, InvalidAddress> {
+		let range = model.address_range();
+		let base = *range.start();
+		match self {
+			Address::Primary => Ok(base),
+			Address::Pins(a0, a1, a2) => Ok(base | (bool::from(a2) as u8) << 2 | (bool::from(a1) as u8) << 1 | bool::from(a0) as u8),
+			Address::Custom(address) if range.contains(&address.get()) => Ok(address.get()),
+			Address::Custom(address) => Err(InvalidAddress(address.get())),
 		}
 	}
-}
\ No newline at end of file
+}
+
+impl TryFrom<u8> for Address {
+	type Error = InvalidAddress;
+
+	fn try_from(address: u8) -> Result<Self, Self::Error> {
+		Self::custom(address)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use LogicLevel::{High, Low};
+
+	#[test]
+	fn test_pin_addresses() {
+		let resolve = |address: Address| address.resolve(Model::PCA9557);
+		assert_eq!(resolve(Address::Primary), Ok(0x18));
+		assert_eq!(resolve(Address::from_pins(Low, Low, Low)), Ok(0x18));
+		assert_eq!(resolve(Address::from_pins(High, Low, High)), Ok(0x1D));
+		assert_eq!(resolve(Address::from_pins(High, High, High)), Ok(0x1F));
+		assert_eq!(Address::from_pins(Low, High, Low).resolve(Model::PCA9554A), Ok(0x3A));
+		assert_eq!(Address::custom(0x1F).and_then(resolve), Ok(0x1F));
+		assert_eq!(Address::custom(0x24), Err(InvalidAddress(0x24)));
+		assert_eq!(Address::custom_for(Model::TCA9554, 0x24).and_then(|address| address.resolve(Model::TCA9554)), Ok(0x24));
+		assert_eq!(Address::try_from(0x17), Err(InvalidAddress(0x17)));
+	}
+
+	#[test]
+	fn test_custom_address_of_other_model() {
+		assert_eq!(Address::custom(0x1F).and_then(|address| address.resolve(Model::PCA9554)), Err(InvalidAddress(0x1F)));
+		assert_eq!(Address::custom_for(Model::TCA9554, 0x24).and_then(|address| address.resolve(Model::PCA9557)), Err(InvalidAddress(0x24)));
+	}
+}
@@ -0,0 +1,272 @@
+//! Scanning of a key matrix wired to a [`PCA9557`], for example a 4x4 keypad with the rows
+//! on IO0 to IO3 and the columns on IO4 to IO7.
+//!
+//! The rows are scanned one at a time by making only that row an output driving low, the
+//! other rows stay inputs so that two keys pressed in the same column never short two
+//! outputs. A pressed key pulls its column low, so the columns need external pull-ups.
+//!
+//! Without diodes in the matrix three keys on the corners of a rectangle make the fourth
+//! corner read as pressed. Scans showing such a rectangle are reported as ghosting and do
+//! not change the key states, any other combination of keys is reported.
+//!
+//! ```ignore
+//! let mut keypad = Keypad::new([0, 1, 2, 3], [4, 5, 6, 7], 5_000, 3);
+//! expander.init_keypad(&keypad).await?;
+//! loop {
+//!     let changes = expander.wait_for_keys(&mut keypad, &mut delay).await?;
+//!     for event in changes.events() {
+//!         // handle KeyEvent::Pressed and KeyEvent::Released
+//!     }
+//! }
+//! ```
+#[cfg(feature = "async")]
+use embedded_registers::RegisterInterfaceAsync;
+#[cfg(feature = "sync")]
+use embedded_registers::RegisterInterfaceSync;
+#[cfg(feature = "async")]
+use crate::devices::expander::ExpanderPort;
+#[cfg(feature = "sync")]
+use crate::devices::expander::ExpanderPortSync;
+#[cfg(feature = "async")]
+use crate::devices::pca9557::PCA9557;
+#[cfg(feature = "sync")]
+use crate::devices::pca9557::PCA9557Sync;
+
+/// Position of a key in the matrix
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub struct Key {
+	pub row: u8,
+	pub col: u8,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum KeyEvent {
+	Pressed(Key),
+	Released(Key),
+}
+
+/// The debounced changes of one scan, bit `row * C + col` belongs to the key at `row`
+/// and `col` of a matrix with `C` columns
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, defmt::Format)]
+pub struct KeyChanges {
+	pub pressed: u16,
+	pub released: u16,
+	/// The scan showed ghosting and was dropped
+	pub ghosting: bool,
+	cols: u8,
+}
+
+impl KeyChanges {
+	pub fn is_empty(&self) -> bool {
+		self.pressed | self.released == 0
+	}
+
+	/// The changes as events, releases first
+	pub fn events(self) -> impl Iterator<Item = KeyEvent> {
+		let key = move |n: u8| Key { row: n / self.cols, col: n % self.cols };
+		let bits = |mask: u16| (0..16).filter(move |n| mask & (1 << n) != 0);
+		bits(self.released)
+			.map(move |n| KeyEvent::Released(key(n)))
+			.chain(bits(self.pressed).map(move |n| KeyEvent::Pressed(key(n))))
+	}
+}
+
+/// Keeps the pin assignment and the debounced key states of a matrix with `R` rows and `C`
+/// columns.
+///
+/// A key only changes its debounced state once its new state was seen in `debounce`
+/// consecutive scans, like the pins of [`super::watcher::InputWatcher`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub struct Keypad<const R: usize, const C: usize> {
+	rows: [u8; R],
+	cols: [u8; C],
+	interval_us: u32,
+	debounce: u8,
+	/// Debounced key states, a set bit is a pressed key
+	state: u16,
+	/// Number of consecutive scans each key differed from its debounced state
+	pending: [u8; 16],
+}
+
+impl<const R: usize, const C: usize> Keypad<R, C> {
+	/// Creates a keypad with the rows on the pins IO `rows[n]` and the columns on the pins
+	/// IO `cols[n]`, scanned every `interval_us` microseconds in [`PCA9557::wait_for_keys`].
+	///
+	/// # Panics
+	/// If the matrix has more than 16 keys or a pin is larger than 7 or used twice.
+	pub fn new(rows: [u8; R], cols: [u8; C], interval_us: u32, debounce: u8) -> Self {
+		assert!(R * C <= 16, "too many keys");
+		let mut pins = 0u8;
+		for &n in rows.iter().chain(cols.iter()) {
+			assert!(n < 8, "pin index out of range");
+			let mask = 1 << n;
+			assert!(pins & mask == 0, "pin used twice");
+			pins |= mask;
+		}
+		Self {
+			rows,
+			cols,
+			interval_us,
+			debounce,
+			state: 0,
+			pending: [0; 16],
+		}
+	}
+
+	/// Pins of the rows, bit n belongs to IO n
+	pub fn row_mask(&self) -> u8 {
+		self.rows.iter().fold(0, |mask, &n| mask | 1 << n)
+	}
+
+	/// Pins of the columns, bit n belongs to IO n
+	pub fn col_mask(&self) -> u8 {
+		self.cols.iter().fold(0, |mask, &n| mask | 1 << n)
+	}
+
+	/// The debounced key states, bit `row * C + col` is set while the key is pressed
+	pub fn pressed(&self) -> u16 {
+		self.state
+	}
+
+	pub fn is_pressed(&self, key: Key) -> bool {
+		self.state & (1 << (key.row as usize * C + key.col as usize)) != 0
+	}
+
+	/// Pressed columns of a row from the `InputPort` value read while the row was driven
+	fn columns(&self, input: u8) -> u8 {
+		self.cols
+			.iter()
+			.enumerate()
+			.filter(|(_, &n)| input & (1 << n) == 0)
+			.fold(0, |pressed, (col, _)| pressed | 1 << col)
+	}
+
+	/// Feeds the pressed columns of each row of a scan, bit n of `scan[row]` is column n
+	pub fn update(&mut self, scan: [u8; R]) -> KeyChanges {
+		let mut changes = KeyChanges {
+			cols: C as u8,
+			..Default::default()
+		};
+		for (n, a) in scan.iter().enumerate() {
+			if scan[n + 1..].iter().any(|b| (a & b).count_ones() > 1) {
+				changes.ghosting = true;
+				return changes;
+			}
+		}
+
+		let now = scan.iter().enumerate().fold(0u16, |now, (row, &cols)| now | (cols as u16) << (row * C));
+		let mut changed = 0;
+		for (n, pending) in self.pending.iter_mut().enumerate().take(R * C) {
+			if (now ^ self.state) & (1 << n) == 0 {
+				*pending = 0;
+				continue;
+			}
+			*pending = pending.saturating_add(1);
+			if *pending >= self.debounce {
+				*pending = 0;
+				changed |= 1 << n;
+			}
+		}
+
+		self.state ^= changed;
+		changes.pressed = changed & self.state;
+		changes.released = changed & !self.state;
+		changes
+	}
+}
+
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), PCA9557(async), RegisterInterface),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> PCA9557<I> {
+	/// Prepares the pins of `keypad`: all of them become inputs without polarity inversion
+	/// and the rows are set to drive low once they are made outputs
+	pub async fn init_keypad<const R: usize, const C: usize>(&mut self, keypad: &Keypad<R, C>) -> Result<(), I::Error> {
+		let pins = keypad.row_mask() | keypad.col_mask();
+		self.write_polarity(self.polarity & !keypad.col_mask()).await?;
+		self.set_pins_masked(keypad.row_mask(), 0x00).await?;
+		self.write_configuration(self.configuration | pins).await
+	}
+
+	/// Scans all rows once and returns the debounced changes
+	pub async fn scan_keypad<const R: usize, const C: usize>(&mut self, keypad: &mut Keypad<R, C>) -> Result<KeyChanges, I::Error> {
+		let mut scan = [0; R];
+		for (row, &n) in keypad.rows.iter().enumerate() {
+			// Only the scanned row is an output, the Configuration register uses 0 for outputs
+			self.write_configuration((self.configuration | keypad.row_mask()) & !(Self::pin_bit(n) as u8)).await?;
+			scan[row] = keypad.columns(self.read_input_raw().await?);
+		}
+		Ok(keypad.update(scan))
+	}
+
+	/// Scans at the interval of the keypad until a key was pressed or released
+	pub async fn wait_for_keys<const R: usize, const C: usize, D: hal::delay::DelayNs>(&mut self, keypad: &mut Keypad<R, C>, delay: &mut D) -> Result<KeyChanges, I::Error> {
+		loop {
+			let changes = self.scan_keypad(keypad).await?;
+			if !changes.is_empty() {
+				return Ok(changes);
+			}
+			delay.delay_us(keypad.interval_us).await;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_rollover_and_ghosting() {
+		let mut keypad = Keypad::new([0, 1, 2, 3], [4, 5, 6, 7], 1000, 2);
+		assert!(keypad.update([0b0001, 0, 0, 0b1000]).is_empty());
+		let changes = keypad.update([0b0001, 0, 0, 0b1000]);
+		assert_eq!(changes.pressed, 0x8001);
+		let mut events = changes.events();
+		assert_eq!(events.next(), Some(KeyEvent::Pressed(Key { row: 0, col: 0 })));
+		assert_eq!(events.next(), Some(KeyEvent::Pressed(Key { row: 3, col: 3 })));
+		assert_eq!(events.next(), None);
+
+		// Three keys on a rectangle make the fourth one appear pressed
+		let changes = keypad.update([0b0011, 0b0011, 0, 0b1000]);
+		assert!(changes.ghosting && changes.is_empty());
+		assert_eq!(keypad.pressed(), 0x8001);
+
+		// Several keys in one row or column are fine
+		keypad.update([0b0111, 0b0001, 0, 0]);
+		let changes = keypad.update([0b0111, 0b0001, 0, 0]);
+		assert_eq!((changes.pressed, changes.released), (0x0016, 0x8000));
+		assert!(keypad.is_pressed(Key { row: 1, col: 0 }));
+		assert_eq!(changes.events().next(), Some(KeyEvent::Released(Key { row: 3, col: 3 })));
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_scan_keypad() {
+		use alloc::vec;
+		use embedded_hal_mock::eh1::delay::NoopDelay;
+		use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+		use crate::devices::pca9557::enums::Address;
+		use crate::test_utils::block_on;
+
+		// Clearing the power-on inversion of the columns, the rows already drive low
+		let mut expectations = vec![Transaction::write(0x18, vec![0x02, 0xC0])];
+		// A first scan without keys, then IO1 pressed on the second row and column
+		for inputs in [[0xFF, 0xFF], [0xFF, 0xDF]] {
+			for (row, input) in inputs.into_iter().enumerate() {
+				expectations.push(Transaction::write(0x18, vec![0x03, !(1 << row)]));
+				expectations.push(Transaction::write_read(0x18, vec![0x00], vec![input]));
+			}
+		}
+		let mut i2c = Mock::new(&expectations);
+		let mut expander = PCA9557::new_i2c(i2c.clone(), Address::Primary).unwrap();
+		let mut keypad = Keypad::new([0, 1], [4, 5], 1000, 1);
+		block_on(async {
+			expander.init_keypad(&keypad).await.unwrap();
+			let changes = expander.wait_for_keys(&mut keypad, &mut NoopDelay).await.unwrap();
+			assert_eq!(changes.events().next(), Some(KeyEvent::Pressed(Key { row: 1, col: 1 })));
+		});
+		i2c.done();
+	}
+}
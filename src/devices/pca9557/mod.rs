@@ -1,90 +1,266 @@
-//! This module contains a driver for the [`PCA9557`] I2C IO expander IC.
+//! This module contains a driver for the [`PCA9557`] I2C IO expander IC and the other 8-bit
+//! parts with the same register map, listed in [`model::Model`].
 //!
 //! [`PCA9557`]: https://www.ti.com/lit/ds/symlink/pca9557.pdf
 //!
 mod registers;
 mod address;
+pub mod model;
+pub mod pins;
+pub mod watcher;
+pub mod pattern;
+pub mod keypad;
+pub mod port;
 
 // This serves to export the enums used by registers
 pub mod enums {
 	pub use super::registers::{Polarity,IODirection};
-	pub use super::address::Address;
+	pub use super::address::{Address, CustomAddress, InvalidAddress};
 }
 
 use crate::devices::pca9557::registers::{Configuration, IODirection};
 use embedded_devices_derive::{device, device_impl};
-use embedded_registers::RegisterInterface;
+#[cfg(feature = "async")]
+use embedded_registers::{i2c::I2cDeviceAsync, RegisterInterfaceAsync};
+#[cfg(feature = "sync")]
+use embedded_registers::{i2c::I2cDeviceSync, RegisterInterfaceSync};
 
+/// Driver for the PCA9557 IO expander and the other parts of [`Model`]. With the `sync`
+/// feature a blocking flavour of this driver is available as `PCA9557Sync`.
+///
+/// The driver keeps shadow copies of the writable registers, so setters write the new
+/// value without reading the register first and skip the write if nothing changed. The
+/// shadows start out with the power-on values, call [`Self::resync`] if the device may
+/// have been configured by someone else.
+///
+/// All pin arrays are indexed by the datasheet pin number, index n is IO n, and so is bit n
+/// of the raw register values.
 #[device]
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), PCA9557(async), RegisterInterface, I2cDevice),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
 pub struct PCA9557<I: RegisterInterface> {
 	interface: I,
+	model: Model,
+	/// Shadow of the `OutputPort` register
+	output: u8,
+	/// Shadow of the `PolarityInversion` register
+	polarity: u8,
+	/// Shadow of the `Configuration` register
+	configuration: u8,
 }
 
 #[device_impl]
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), PCA9557(async), ExpanderPort(async), RegisterInterface, I2cDevice),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
 impl<I: RegisterInterface> PCA9557<I> {
 
+	/// The part this driver was created for
+	pub fn model(&self) -> Model {
+		self.model
+	}
+
 	pub async fn get_input_port(&mut self) -> Result<[LogicLevel;8], I::Error> {
-		 Ok(self.read_register::<InputPort>().await?.read_pins())
+		Ok(unpack(self.read_input_raw().await? as u16))
 	}
 
 	pub async fn get_output_port(&mut self) -> Result<[LogicLevel;8], I::Error> {
-		Ok(self.read_register::<OutputPort>().await?.read_pins())
+		Ok(unpack(self.read_shadowed(PortRegister::Output).await?))
 	}
 	
 	pub async fn get_polarity(&mut self) -> Result<[Polarity;8], I::Error> {
-		Ok(self.read_register::<PolarityInversion>().await?.read_pins())
+		Ok(unpack(self.read_shadowed(PortRegister::Polarity).await?))
 	}
 
 	pub async fn get_configuration(&mut self) -> Result<[IODirection;8], I::Error> {
-		Ok(self.read_register::<Configuration>().await?.read_pins())
+		Ok(unpack(self.read_shadowed(PortRegister::Configuration).await?))
 	}
 
-	pub async fn set_output_port(&mut self, mut input: [LogicLevel;8]) -> Result<(), I::Error> {
-		input.reverse();
-		let mut reg = self.read_register::<OutputPort>().await?;
-		reg.write_pins(input);
-		self.write_register(reg).await?;
-		Ok(())
+	pub async fn set_output_port(&mut self, input: [LogicLevel;8]) -> Result<(), I::Error> {
+		self.write_output(pack(input) as u8).await
 	}
 	
-	pub async fn set_polarity(&mut self, mut input: [Polarity;8]) -> Result<(), I::Error> {
-		input.reverse(); // reverse the input so that the index used by the user matches the chip
-		let mut reg = self.read_register::<PolarityInversion>().await?;
-		reg.write_pins(input);
-		self.write_register(reg).await?;
-		Ok(())
+	pub async fn set_polarity(&mut self, input: [Polarity;8]) -> Result<(), I::Error> {
+		self.write_polarity(pack(input) as u8).await
 	}
 	
-	pub async fn set_configuration(&mut self,mut input: [IODirection;8]) -> Result<(), I::Error> {
-		input.reverse(); // reverse the input so that the index used by the user matches the chip
-		let mut reg = self.read_register::<Configuration>().await?;
-		reg.write_pins(input);
-		self.write_register(reg).await?;
-		Ok(())
+	pub async fn set_configuration(&mut self, input: [IODirection;8]) -> Result<(), I::Error> {
+		self.write_configuration(pack(input) as u8).await
+	}
+
+	/// Writes the `OutputPort` register if `value` differs from its shadow
+	async fn write_output(&mut self, value: u8) -> Result<(), I::Error> {
+		self.write_shadowed(PortRegister::Output, value as u16).await
+	}
+
+	/// Writes the `PolarityInversion` register if `value` differs from its shadow
+	async fn write_polarity(&mut self, value: u8) -> Result<(), I::Error> {
+		self.write_shadowed(PortRegister::Polarity, value as u16).await
+	}
+
+	/// Writes the `Configuration` register if `value` differs from its shadow
+	async fn write_configuration(&mut self, value: u8) -> Result<(), I::Error> {
+		self.write_shadowed(PortRegister::Configuration, value as u16).await
+	}
+
+	/// Reads the writable registers back into their shadows, for example after the device
+	/// was reset or another bus master changed them
+	pub async fn resync(&mut self) -> Result<(), I::Error> {
+		self.resync_shadows().await
+	}
+
+	/// Reads the `InputPort` register, bit n holds the level of IO n
+	pub async fn read_input_raw(&mut self) -> Result<u8, I::Error> {
+		Ok(self.read_register::<InputPort>().await?.data[0])
+	}
+
+	/// The value last written to the `OutputPort` register, bit n drives IO n
+	pub fn output_raw(&self) -> u8 {
+		self.output
+	}
+
+	/// Writes the `OutputPort` register, bit n drives IO n
+	pub async fn write_output_raw(&mut self, value: u8) -> Result<(), I::Error> {
+		self.write_output(value).await
+	}
+
+	/// Writes the `PolarityInversion` register, a set bit n inverts the input IO n
+	pub async fn write_polarity_raw(&mut self, value: u8) -> Result<(), I::Error> {
+		self.write_polarity(value).await
+	}
+
+	/// Writes the `Configuration` register, a set bit n makes IO n an input
+	pub async fn write_configuration_raw(&mut self, value: u8) -> Result<(), I::Error> {
+		self.write_configuration(value).await
+	}
+
+	/// Reads the input level of IO `n`.
+	///
+	/// # Panics
+	/// If `n` is larger than 7.
+	pub async fn read_pin(&mut self, n: u8) -> Result<LogicLevel, I::Error> {
+		self.read_input_pin(n).await
+	}
+
+	/// Drives IO `n` to `level`, the other outputs keep their level.
+	///
+	/// # Panics
+	/// If `n` is larger than 7.
+	pub async fn set_pin(&mut self, n: u8, level: LogicLevel) -> Result<(), I::Error> {
+		self.write_output_pin(n, level).await
+	}
+
+	/// Inverts the output level of IO `n`.
+	///
+	/// # Panics
+	/// If `n` is larger than 7.
+	pub async fn toggle_pin(&mut self, n: u8) -> Result<(), I::Error> {
+		self.toggle_output_pin(n).await
+	}
+
+	/// Drives the outputs selected by the set bits of `mask` to the corresponding bits of
+	/// `value` in a single write, the other outputs keep their level
+	pub async fn set_pins_masked(&mut self, mask: u8, value: u8) -> Result<(), I::Error> {
+		self.write_output_masked(mask as u16, value as u16).await
+	}
+}
+
+#[maybe_async_cfg::maybe(
+	idents(PCA9557(async), ExpanderPort(async), RegisterInterface),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> ExpanderPort for PCA9557<I> {
+	type Error = I::Error;
+	const PINS: u8 = 8;
+
+	async fn read_input(&mut self) -> Result<u16, I::Error> {
+		Ok(self.read_input_raw().await? as u16)
+	}
+
+	async fn read_port(&mut self, register: PortRegister) -> Result<u16, I::Error> {
+		let value = match register {
+			PortRegister::Output => self.read_register::<OutputPort>().await?.data[0],
+			PortRegister::Polarity => self.read_register::<PolarityInversion>().await?.data[0],
+			PortRegister::Configuration => self.read_register::<Configuration>().await?.data[0],
+		};
+		Ok(value as u16)
+	}
+
+	async fn write_port(&mut self, register: PortRegister, value: u16) -> Result<(), I::Error> {
+		let data = [value as u8];
+		match register {
+			PortRegister::Output => self.write_register(OutputPort { data }).await,
+			PortRegister::Polarity => self.write_register(PolarityInversion { data }).await,
+			PortRegister::Configuration => self.write_register(Configuration { data }).await,
+		}
 	}
 
+	fn shadow(&self, register: PortRegister) -> u16 {
+		match register {
+			PortRegister::Output => self.output as u16,
+			PortRegister::Polarity => self.polarity as u16,
+			PortRegister::Configuration => self.configuration as u16,
+		}
+	}
 
+	fn set_shadow(&mut self, register: PortRegister, value: u16) {
+		let value = value as u8;
+		match register {
+			PortRegister::Output => self.output = value,
+			PortRegister::Polarity => self.polarity = value,
+			PortRegister::Configuration => self.configuration = value,
+		}
+	}
 }
 
-use embedded_hal_async as hal;
-use embedded_registers::i2c::I2cDevice;
 use crate::common::enums::LogicLevel;
-use crate::devices::pca9557::address::Address;
+use crate::devices::pca9557::address::{Address, InvalidAddress};
 use crate::devices::pca9557::registers::{InputPort, OutputPort, Polarity, PolarityInversion};
 
+use crate::devices::expander::{pack, unpack, PortRegister};
+#[cfg(feature = "async")]
+use crate::devices::expander::ExpanderPort;
+#[cfg(feature = "sync")]
+use crate::devices::expander::ExpanderPortSync;
+use crate::devices::pca9557::model::Model;
+
 type PCA9557Codec = embedded_registers::i2c::codecs::OneByteRegAddrCodec;
 
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), PCA9557(async), RegisterInterface, I2cDevice),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
 impl<I> PCA9557<I2cDevice<I,hal::i2c::SevenBitAddress,PCA9557Codec>>
 where I: hal::i2c::I2c<hal::i2c::SevenBitAddress> + hal::i2c::ErrorType{
 	/// Initializes a new device with the given address on the specified bus.
 	/// This consumes the I2C bus `I`.
 	///
-	/// Before using this device, you must call the [`Self::init`] method which
-	/// initializes the device and ensures that it is working correctly.
-	pub fn new_i2c(interface: I, address: Address) -> Self {
-		Self {
-			interface: I2cDevice::new(interface,address.into(), PCA9557Codec::default()),
-		}
+	/// The shadow registers assume the device is in its power-on state, call
+	/// [`Self::resync`] if it may have been configured before.
+	///
+	/// Fails if `address` is a custom address checked for a model with a different address
+	/// range, see [`Address::custom_for`].
+	pub fn new_i2c(interface: I, address: Address) -> Result<Self, InvalidAddress> {
+		Self::new_i2c_model(interface, address, Model::default())
+	}
+
+	/// Like [`Self::new_i2c`] for one of the other parts with the same register map
+	pub fn new_i2c_model(interface: I, address: Address, model: Model) -> Result<Self, InvalidAddress> {
+		let power_on = model.power_on();
+		Ok(Self {
+			interface: I2cDevice::new(interface,address.resolve(model)?),
+			model,
+			output: power_on.output as u8,
+			polarity: power_on.polarity as u8,
+			configuration: power_on.configuration as u8,
+		})
 	}
 
 
@@ -112,4 +288,100 @@ mod tests {
 				"We are expecting {:#010b} and actually got {:#010b}",inversion_check,test_config.data[0]
 				);
 	}
-}
\ No newline at end of file
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_shadow_registers() {
+		use alloc::vec;
+		use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+		use crate::test_utils::block_on;
+
+		let expectations = [
+			Transaction::write(0x18, vec![0x01, 0x80]),
+			Transaction::write_read(0x18, vec![0x01], vec![0x01]),
+			Transaction::write_read(0x18, vec![0x02], vec![0x00]),
+			Transaction::write_read(0x18, vec![0x03], vec![0x00]),
+			Transaction::write(0x18, vec![0x01, 0x80]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let mut expander = PCA9557::new_i2c(i2c.clone(), Address::Primary).unwrap();
+		let mut levels = [LogicLevel::Low; 8];
+		levels[7] = LogicLevel::High;
+		block_on(async {
+			// Only the first write changes the register
+			expander.set_output_port(levels).await.unwrap();
+			expander.set_output_port(levels).await.unwrap();
+			// Someone else changed the register, after a resync it is written again
+			expander.resync().await.unwrap();
+			expander.set_output_port(levels).await.unwrap();
+		});
+		i2c.done();
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_single_pins() {
+		use alloc::vec;
+		use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+		use crate::test_utils::block_on;
+
+		let expectations = [
+			Transaction::write(0x18, vec![0x01, 0x08]),
+			Transaction::write(0x18, vec![0x01, 0x09]),
+			Transaction::write(0x18, vec![0x01, 0xA9]),
+			Transaction::write_read(0x18, vec![0x00], vec![0x40]),
+			Transaction::write_read(0x18, vec![0x00], vec![0x40]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let mut expander = PCA9557::new_i2c(i2c.clone(), Address::Primary).unwrap();
+		block_on(async {
+			expander.set_pin(3, LogicLevel::High).await.unwrap();
+			expander.set_pin(3, LogicLevel::High).await.unwrap();
+			expander.toggle_pin(0).await.unwrap();
+			expander.set_pins_masked(0xF0, 0xAF).await.unwrap();
+			assert_eq!(expander.output_raw(), 0xA9);
+			assert_eq!(expander.read_pin(6).await.unwrap(), LogicLevel::High);
+			assert_eq!(expander.read_pin(7).await.unwrap(), LogicLevel::Low);
+		});
+		i2c.done();
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_port_round_trip() {
+		use alloc::vec;
+		use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+		use crate::test_utils::block_on;
+
+		// IO1, IO2, IO5 and IO7 are set
+		const PATTERN: u8 = 0b1010_0110;
+		fn pins<T: Copy>(set: T, clear: T) -> [T; 8] {
+			core::array::from_fn(|n| if PATTERN & (1 << n) != 0 { set } else { clear })
+		}
+
+		let expectations = [
+			Transaction::write_read(0x18, vec![0x00], vec![PATTERN]),
+			Transaction::write(0x18, vec![0x01, PATTERN]),
+			Transaction::write_read(0x18, vec![0x01], vec![PATTERN]),
+			Transaction::write(0x18, vec![0x02, PATTERN]),
+			Transaction::write_read(0x18, vec![0x02], vec![PATTERN]),
+			Transaction::write(0x18, vec![0x03, PATTERN]),
+			Transaction::write_read(0x18, vec![0x03], vec![PATTERN]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let mut expander = PCA9557::new_i2c(i2c.clone(), Address::Primary).unwrap();
+		let levels = pins(LogicLevel::High, LogicLevel::Low);
+		let polarity = pins(Polarity::Inverted, Polarity::Original);
+		let directions = pins(IODirection::Input, IODirection::Output);
+		block_on(async {
+			assert_eq!(expander.get_input_port().await.unwrap(), levels);
+			expander.set_output_port(levels).await.unwrap();
+			assert_eq!(expander.get_output_port().await.unwrap(), levels);
+			expander.set_polarity(polarity).await.unwrap();
+			assert_eq!(expander.get_polarity().await.unwrap(), polarity);
+			expander.set_configuration(directions).await.unwrap();
+			assert_eq!(expander.get_configuration().await.unwrap(), directions);
+		});
+		i2c.done();
+	}
+}
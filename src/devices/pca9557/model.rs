@@ -0,0 +1,51 @@
+use core::ops::RangeInclusive;
+use crate::devices::expander::PowerOn;
+
+/// The 8-bit parts sharing the register map of the PCA9557
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, defmt::Format)]
+pub enum Model {
+	/// Polarity inversion of IO4 to IO7 after power-on, open-drain IO0, a reset pin instead
+	/// of an interrupt output
+	#[default]
+	PCA9557,
+	PCA9554,
+	/// PCA9554 with a different address range
+	PCA9554A,
+	PCA9534,
+	/// PCA9534 with a different address range
+	PCA9534A,
+	TCA9554,
+	/// TCA9554 with a different address range
+	TCA9554A,
+}
+
+impl Model {
+	/// The I2C addresses selectable with the A0 to A2 pins
+	pub fn address_range(self) -> RangeInclusive<u8> {
+		match self {
+			Model::PCA9557 => 0x18..=0x1F,
+			Model::PCA9554 | Model::PCA9534 | Model::TCA9554 => 0x20..=0x27,
+			Model::PCA9554A | Model::PCA9534A | Model::TCA9554A => 0x38..=0x3F,
+		}
+	}
+
+	pub fn power_on(self) -> PowerOn {
+		match self {
+			Model::PCA9557 => PowerOn { output: 0x00, polarity: 0xF0, configuration: 0xFF },
+			_ => PowerOn { output: 0xFF, polarity: 0x00, configuration: 0xFF },
+		}
+	}
+
+	/// Whether the part has an interrupt output signalling input changes
+	pub fn has_interrupt(self) -> bool {
+		self != Model::PCA9557
+	}
+
+	/// Outputs that can only drive low, bit n belongs to IO n
+	pub fn open_drain_pins(self) -> u8 {
+		match self {
+			Model::PCA9557 => 0x01,
+			_ => 0x00,
+		}
+	}
+}
@@ -0,0 +1,275 @@
+//! Blink, breathing and error code patterns on the outputs of a [`PCA9557`], without a
+//! hardware timer per LED.
+//!
+//! A [`PatternEngine`] counts ticks of a fixed length and computes the level of every pin it
+//! drives from the pattern of that pin. Each tick ends in at most one `OutputPort` write
+//! carrying all changed pins, ticks without a change do not touch the bus.
+//!
+//! ```ignore
+//! let mut engine = PatternEngine::new(10_000);
+//! engine.set(0, Pattern::blink(50, 50));
+//! engine.set(1, Pattern::Code { count: 3, on: 20, off: 30, pause: 150 });
+//! engine.set(2, Pattern::Breathe { period: 2, ramp: 100 });
+//! loop {
+//!     expander.run_patterns(&mut engine, 100, &mut delay).await?;
+//! }
+//! ```
+#[cfg(feature = "async")]
+use embedded_registers::RegisterInterfaceAsync;
+#[cfg(feature = "sync")]
+use embedded_registers::RegisterInterfaceSync;
+#[cfg(feature = "async")]
+use crate::devices::pca9557::PCA9557;
+#[cfg(feature = "sync")]
+use crate::devices::pca9557::PCA9557Sync;
+
+/// The levels of a pin over time, all durations are in ticks
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum Pattern {
+	Off,
+	On,
+	/// On for the first `duty` ticks of every `period` ticks, blinking or low-rate PWM. The
+	/// period is wider than the duty so that it holds any on and off phase of a blink.
+	Pwm { period: u32, duty: u16 },
+	/// PWM with a period of `period` ticks whose duty cycle ramps from 0 to 100% over `ramp`
+	/// periods and back down
+	Breathe { period: u16, ramp: u16 },
+	/// `count` blinks followed by a pause, for error codes
+	Code { count: u8, on: u16, off: u16, pause: u16 },
+	/// Durations of alternating on and off phases, starting with on, repeated
+	Sequence(&'static [u16]),
+}
+
+impl Pattern {
+	/// On for `on` ticks, then off for `off` ticks
+	pub const fn blink(on: u16, off: u16) -> Self {
+		Pattern::Pwm { period: on as u32 + off as u32, duty: on }
+	}
+
+	/// Whether the pin is on `tick` ticks after the pattern started
+	pub fn is_on(&self, tick: u32) -> bool {
+		match *self {
+			Pattern::Off => false,
+			Pattern::On => true,
+			Pattern::Pwm { period, duty } => period != 0 && tick % period < duty as u32,
+			Pattern::Breathe { period, ramp } => {
+				if period == 0 || ramp == 0 {
+					return false;
+				}
+				let (period, ramp) = (period as u32, ramp as u32);
+				let frame = (tick / period) % (2 * ramp);
+				let level = if frame < ramp { frame } else { 2 * ramp - frame };
+				tick % period < level * period / ramp
+			}
+			Pattern::Code { count, on, off, pause } => {
+				let blink = on as u32 + off as u32;
+				let blinks = blink * count as u32;
+				let cycle = blinks + pause as u32;
+				if cycle == 0 {
+					return false;
+				}
+				let t = tick % cycle;
+				t < blinks && t % blink < (on as u32)
+			}
+			Pattern::Sequence(phases) => {
+				let cycle: u32 = phases.iter().map(|&p| p as u32).sum();
+				if cycle == 0 {
+					return false;
+				}
+				let mut t = tick % cycle;
+				for (n, &phase) in phases.iter().enumerate() {
+					if t < (phase as u32) {
+						return n % 2 == 0;
+					}
+					t -= phase as u32;
+				}
+				false
+			}
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+struct Slot {
+	pattern: Pattern,
+	/// Tick at which the pattern started
+	start: u32,
+}
+
+/// Schedules a [`Pattern`] on each of the pins IO0 to IO7.
+///
+/// The engine only drives the pins it has a pattern for, the other outputs keep their level.
+/// The pins still have to be configured as outputs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub struct PatternEngine {
+	tick_us: u32,
+	tick: u32,
+	slots: [Option<Slot>; 8],
+	/// Pins that are driven low to turn them on
+	active_low: u8,
+}
+
+impl PatternEngine {
+	/// Creates an engine without patterns, advancing by one tick every `tick_us`
+	/// microseconds in [`PCA9557::run_patterns`]
+	pub fn new(tick_us: u32) -> Self {
+		Self {
+			tick_us,
+			tick: 0,
+			slots: [None; 8],
+			active_low: 0,
+		}
+	}
+
+	pub fn tick_us(&self) -> u32 {
+		self.tick_us
+	}
+
+	/// Number of ticks since the engine was created
+	pub fn tick(&self) -> u32 {
+		self.tick
+	}
+
+	/// Pins whose LED is on while the pin is low, bit n belongs to IO n
+	pub fn set_active_low(&mut self, mask: u8) {
+		self.active_low = mask;
+	}
+
+	/// Starts `pattern` on IO `n` from its beginning.
+	///
+	/// # Panics
+	/// If `n` is larger than 7.
+	pub fn set(&mut self, n: u8, pattern: Pattern) {
+		assert!(n < 8, "pin index out of range");
+		self.slots[n as usize] = Some(Slot { pattern, start: self.tick });
+	}
+
+	/// Stops driving IO `n`, it keeps its last level.
+	///
+	/// # Panics
+	/// If `n` is larger than 7.
+	pub fn clear(&mut self, n: u8) {
+		assert!(n < 8, "pin index out of range");
+		self.slots[n as usize] = None;
+	}
+
+	/// The pattern running on IO `n`
+	pub fn pattern(&self, n: u8) -> Option<Pattern> {
+		self.slots.get(n as usize).copied().flatten().map(|slot| slot.pattern)
+	}
+
+	/// Pins driven by a pattern, bit n belongs to IO n
+	pub fn mask(&self) -> u8 {
+		self.slots
+			.iter()
+			.enumerate()
+			.filter(|(_, slot)| slot.is_some())
+			.fold(0, |mask, (n, _)| mask | 1 << n)
+	}
+
+	/// Output levels of the current tick for the pins in [`Self::mask`]
+	pub fn levels(&self) -> u8 {
+		let on = self.slots.iter().enumerate().fold(0u8, |on, (n, slot)| match slot {
+			Some(slot) if slot.pattern.is_on(self.tick.wrapping_sub(slot.start)) => on | 1 << n,
+			_ => on,
+		});
+		(on ^ self.active_low) & self.mask()
+	}
+
+	/// Returns the mask and levels of the current tick and moves on to the next one
+	pub fn next_outputs(&mut self) -> (u8, u8) {
+		let outputs = (self.mask(), self.levels());
+		self.tick = self.tick.wrapping_add(1);
+		outputs
+	}
+}
+
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), PCA9557(async), RegisterInterface),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> PCA9557<I> {
+	/// Writes the outputs of the current tick of `engine` and advances it by one tick, for
+	/// driving the engine from a timer of the application
+	pub async fn pattern_tick(&mut self, engine: &mut PatternEngine) -> Result<(), I::Error> {
+		let (mask, levels) = engine.next_outputs();
+		self.set_pins_masked(mask, levels).await
+	}
+
+	/// Runs `ticks` ticks of `engine`, waiting the tick length of the engine after each. The
+	/// time spent on the bus adds to the tick length.
+	pub async fn run_patterns<D: hal::delay::DelayNs>(&mut self, engine: &mut PatternEngine, ticks: u32, delay: &mut D) -> Result<(), I::Error> {
+		for _ in 0..ticks {
+			self.pattern_tick(engine).await?;
+			delay.delay_us(engine.tick_us()).await;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn trace(pattern: Pattern, ticks: u32) -> u32 {
+		(0..ticks).fold(0, |bits, t| bits | (pattern.is_on(t) as u32) << t)
+	}
+
+	#[test]
+	fn test_patterns() {
+		assert_eq!(trace(Pattern::blink(2, 3), 10), 0b00011_00011);
+		assert_eq!(trace(Pattern::Code { count: 2, on: 1, off: 1, pause: 3 }, 10), 0b101_0000101);
+		assert_eq!(trace(Pattern::Sequence(&[1, 2, 3, 1]), 14), 0b0111001_0111001);
+		assert_eq!(trace(Pattern::Pwm { period: 0, duty: 0 }, 4), 0);
+		assert_eq!(Pattern::blink(u16::MAX, 1), Pattern::Pwm { period: 0x1_0000, duty: u16::MAX });
+		assert!(!Pattern::blink(u16::MAX, 1).is_on(u16::MAX as u32));
+
+		// The duty cycle goes 0, 1/2, 1 and back down with two ticks per period
+		let breathe = Pattern::Breathe { period: 2, ramp: 2 };
+		assert_eq!(trace(breathe, 8), 0b01_11_01_00);
+	}
+
+	#[test]
+	fn test_engine_levels() {
+		let mut engine = PatternEngine::new(1000);
+		engine.set(1, Pattern::On);
+		engine.set(6, Pattern::blink(1, 1));
+		engine.set_active_low(0x02);
+		assert_eq!(engine.mask(), 0x42);
+		assert_eq!(engine.next_outputs(), (0x42, 0x40));
+		assert_eq!(engine.next_outputs(), (0x42, 0x00));
+
+		// A pattern starts from its beginning when set
+		engine.set(3, Pattern::blink(1, 1));
+		assert_eq!(engine.next_outputs(), (0x4A, 0x48));
+		engine.clear(6);
+		assert_eq!(engine.pattern(6), None);
+		assert_eq!(engine.next_outputs(), (0x0A, 0x00));
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_run_patterns() {
+		use alloc::vec;
+		use embedded_hal_mock::eh1::delay::NoopDelay;
+		use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+		use crate::devices::pca9557::enums::Address;
+		use crate::test_utils::block_on;
+
+		// Changes of a tick share one write, ticks without changes are skipped
+		let expectations = [
+			Transaction::write(0x18, vec![0x01, 0x29]),
+			Transaction::write(0x18, vec![0x01, 0x20]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let mut expander = PCA9557::new_i2c(i2c.clone(), Address::Primary).unwrap();
+		let mut engine = PatternEngine::new(1000);
+		engine.set(0, Pattern::blink(2, 2));
+		engine.set(3, Pattern::blink(2, 2));
+		engine.set(5, Pattern::On);
+		block_on(expander.run_patterns(&mut engine, 4, &mut NoopDelay)).unwrap();
+		assert_eq!(engine.tick(), 4);
+		i2c.done();
+	}
+}
@@ -0,0 +1,356 @@
+//! Individual pin handles of a shared [`PCA9557`], so that single pins can be handed to other
+//! drivers, for example as chip select or reset line.
+//!
+//! The blocking flavour shares the device through a [`RefCell`](core::cell::RefCell) and
+//! implements the `embedded_hal::digital` traits, the async flavour shares it through an
+//! `embassy_sync` [`Mutex`] and offers the same operations as async methods.
+//!
+//! ```ignore
+//! let expander = RefCell::new(PCA9557Sync::new_i2c(i2c, Address::Primary)?);
+//! let [io0, io1, ..] = PCA9557Sync::split(&expander)?;
+//! let mut reset = io0.into_output()?;
+//! reset.set_low()?;
+//! ```
+#[cfg(feature = "sync")]
+use core::cell::RefCell;
+use core::marker::PhantomData;
+#[cfg(feature = "sync")]
+use crate::common::enums::LogicLevel;
+#[cfg(feature = "async")]
+use embassy_sync::{blocking_mutex::raw::RawMutex, mutex::Mutex};
+#[cfg(feature = "async")]
+use embedded_registers::RegisterInterfaceAsync;
+#[cfg(feature = "sync")]
+use embedded_registers::RegisterInterfaceSync;
+#[cfg(feature = "async")]
+use crate::devices::expander::ExpanderPort;
+#[cfg(feature = "sync")]
+use crate::devices::expander::ExpanderPortSync;
+#[cfg(feature = "async")]
+use crate::devices::pca9557::PCA9557;
+#[cfg(feature = "sync")]
+use crate::devices::pca9557::PCA9557Sync;
+
+/// Type state of a pin configured as input
+pub struct Input;
+/// Type state of a pin configured as output
+pub struct Output;
+
+/// Error of a pin handle, wrapping the error of the bus
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum PinError<E> {
+	Bus(E),
+}
+
+impl<E: core::fmt::Debug> embedded_hal::digital::Error for PinError<E> {
+	fn kind(&self) -> embedded_hal::digital::ErrorKind {
+		embedded_hal::digital::ErrorKind::Other
+	}
+}
+
+#[maybe_async_cfg::maybe(
+	idents(PCA9557(async), RegisterInterface),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> PCA9557<I> {
+	/// Makes IO `index` an output or an input. Inputs also get their polarity inversion
+	/// cleared, so that reads return the level at the pin.
+	async fn write_direction_bit(&mut self, index: u8, output: bool) -> Result<(), I::Error> {
+		let bit = Self::pin_bit(index) as u8;
+		if !output {
+			self.write_polarity(self.polarity & !bit).await?;
+		}
+		// The Configuration register uses 0 for outputs
+		let configuration = if output { self.configuration & !bit } else { self.configuration | bit };
+		self.write_configuration(configuration).await
+	}
+
+	/// Whether IO `index` is set to drive high
+	fn is_output_high(&self, index: u8) -> bool {
+		self.output & Self::pin_bit(index) as u8 != 0
+	}
+}
+
+/// A single pin of a [`PCA9557Sync`], created by [`PCA9557Sync::split`]
+#[cfg(feature = "sync")]
+pub struct PinSync<'a, I: RegisterInterfaceSync, MODE> {
+	pub(super) device: &'a RefCell<PCA9557Sync<I>>,
+	pub(super) index: u8,
+	_mode: PhantomData<MODE>,
+}
+
+#[cfg(feature = "sync")]
+impl<I: RegisterInterfaceSync> PCA9557Sync<I> {
+	/// Splits the device into its pins IO0 to IO7. The handles start out as inputs, matching
+	/// the power-on state, and only access the device while an operation runs.
+	///
+	/// The polarity inversion of all pins is cleared, since some parts power up with
+	/// inverted inputs.
+	pub fn split(device: &RefCell<Self>) -> Result<[PinSync<'_, I, Input>; 8], PinError<I::Error>> {
+		device.borrow_mut().write_polarity(0).map_err(PinError::Bus)?;
+		Ok(core::array::from_fn(|index| PinSync {
+			device,
+			index: index as u8,
+			_mode: PhantomData,
+		}))
+	}
+}
+
+#[cfg(feature = "sync")]
+impl<'a, I: RegisterInterfaceSync, MODE> PinSync<'a, I, MODE> {
+	/// The datasheet number n of the pin IO n
+	pub fn index(&self) -> u8 {
+		self.index
+	}
+
+	fn with_direction<M>(self, output: bool) -> Result<PinSync<'a, I, M>, PinError<I::Error>> {
+		self.device.borrow_mut().write_direction_bit(self.index, output).map_err(PinError::Bus)?;
+		Ok(PinSync {
+			device: self.device,
+			index: self.index,
+			_mode: PhantomData,
+		})
+	}
+
+	pub fn into_output(self) -> Result<PinSync<'a, I, Output>, PinError<I::Error>> {
+		self.with_direction(true)
+	}
+
+	/// Writes the output level before switching the direction, so the pin does not glitch
+	pub fn into_output_with_state(self, state: embedded_hal::digital::PinState) -> Result<PinSync<'a, I, Output>, PinError<I::Error>> {
+		self.device.borrow_mut().set_pin(self.index, bool::from(state).into()).map_err(PinError::Bus)?;
+		self.with_direction(true)
+	}
+
+	pub fn into_input(self) -> Result<PinSync<'a, I, Input>, PinError<I::Error>> {
+		self.with_direction(false)
+	}
+}
+
+#[cfg(feature = "sync")]
+impl<I: RegisterInterfaceSync, MODE> embedded_hal::digital::ErrorType for PinSync<'_, I, MODE>
+where
+	I::Error: core::fmt::Debug,
+{
+	type Error = PinError<I::Error>;
+}
+
+#[cfg(feature = "sync")]
+impl<I: RegisterInterfaceSync> embedded_hal::digital::InputPin for PinSync<'_, I, Input>
+where
+	I::Error: core::fmt::Debug,
+{
+	fn is_high(&mut self) -> Result<bool, Self::Error> {
+		self.device.borrow_mut().read_pin(self.index).map(bool::from).map_err(PinError::Bus)
+	}
+
+	fn is_low(&mut self) -> Result<bool, Self::Error> {
+		self.is_high().map(|high| !high)
+	}
+}
+
+#[cfg(feature = "sync")]
+impl<I: RegisterInterfaceSync> embedded_hal::digital::OutputPin for PinSync<'_, I, Output>
+where
+	I::Error: core::fmt::Debug,
+{
+	fn set_low(&mut self) -> Result<(), Self::Error> {
+		self.device.borrow_mut().set_pin(self.index, LogicLevel::Low).map_err(PinError::Bus)
+	}
+
+	fn set_high(&mut self) -> Result<(), Self::Error> {
+		self.device.borrow_mut().set_pin(self.index, LogicLevel::High).map_err(PinError::Bus)
+	}
+}
+
+#[cfg(feature = "sync")]
+impl<I: RegisterInterfaceSync> embedded_hal::digital::StatefulOutputPin for PinSync<'_, I, Output>
+where
+	I::Error: core::fmt::Debug,
+{
+	fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+		Ok(self.device.borrow().is_output_high(self.index))
+	}
+
+	fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+		self.is_set_high().map(|high| !high)
+	}
+}
+
+/// A single pin of a [`PCA9557`], created by [`PCA9557::split`]
+#[cfg(feature = "async")]
+pub struct Pin<'a, M: RawMutex, I: RegisterInterfaceAsync, MODE> {
+	pub(super) device: &'a Mutex<M, PCA9557<I>>,
+	pub(super) index: u8,
+	_mode: PhantomData<MODE>,
+}
+
+#[cfg(feature = "async")]
+impl<I: RegisterInterfaceAsync> PCA9557<I> {
+	/// Splits the device into its pins IO0 to IO7. The handles start out as inputs, matching
+	/// the power-on state, and only lock the device while an operation runs.
+	///
+	/// The polarity inversion of all pins is cleared, since some parts power up with
+	/// inverted inputs.
+	pub async fn split<M: RawMutex>(device: &Mutex<M, Self>) -> Result<[Pin<'_, M, I, Input>; 8], PinError<I::Error>> {
+		device.lock().await.write_polarity(0).await.map_err(PinError::Bus)?;
+		Ok(core::array::from_fn(|index| Pin {
+			device,
+			index: index as u8,
+			_mode: PhantomData,
+		}))
+	}
+}
+
+#[cfg(feature = "async")]
+impl<'a, M: RawMutex, I: RegisterInterfaceAsync, MODE> Pin<'a, M, I, MODE> {
+	/// The datasheet number n of the pin IO n
+	pub fn index(&self) -> u8 {
+		self.index
+	}
+
+	async fn with_direction<T>(self, output: bool) -> Result<Pin<'a, M, I, T>, PinError<I::Error>> {
+		self.device.lock().await.write_direction_bit(self.index, output).await.map_err(PinError::Bus)?;
+		Ok(Pin {
+			device: self.device,
+			index: self.index,
+			_mode: PhantomData,
+		})
+	}
+
+	pub async fn into_output(self) -> Result<Pin<'a, M, I, Output>, PinError<I::Error>> {
+		self.with_direction(true).await
+	}
+
+	/// Writes the output level before switching the direction, so the pin does not glitch
+	pub async fn into_output_with_state(self, state: embedded_hal::digital::PinState) -> Result<Pin<'a, M, I, Output>, PinError<I::Error>> {
+		self.device.lock().await.set_pin(self.index, bool::from(state).into()).await.map_err(PinError::Bus)?;
+		self.with_direction(true).await
+	}
+
+	pub async fn into_input(self) -> Result<Pin<'a, M, I, Input>, PinError<I::Error>> {
+		self.with_direction(false).await
+	}
+}
+
+#[cfg(feature = "async")]
+impl<M: RawMutex, I: RegisterInterfaceAsync> Pin<'_, M, I, Input> {
+	pub async fn is_high(&mut self) -> Result<bool, PinError<I::Error>> {
+		self.device.lock().await.read_pin(self.index).await.map(bool::from).map_err(PinError::Bus)
+	}
+
+	pub async fn is_low(&mut self) -> Result<bool, PinError<I::Error>> {
+		self.is_high().await.map(|high| !high)
+	}
+}
+
+#[cfg(feature = "async")]
+impl<M: RawMutex, I: RegisterInterfaceAsync> Pin<'_, M, I, Output> {
+	pub async fn set_state(&mut self, state: embedded_hal::digital::PinState) -> Result<(), PinError<I::Error>> {
+		self.device.lock().await.set_pin(self.index, bool::from(state).into()).await.map_err(PinError::Bus)
+	}
+
+	pub async fn set_low(&mut self) -> Result<(), PinError<I::Error>> {
+		self.set_state(embedded_hal::digital::PinState::Low).await
+	}
+
+	pub async fn set_high(&mut self) -> Result<(), PinError<I::Error>> {
+		self.set_state(embedded_hal::digital::PinState::High).await
+	}
+
+	pub async fn is_set_high(&mut self) -> Result<bool, PinError<I::Error>> {
+		Ok(self.device.lock().await.is_output_high(self.index))
+	}
+
+	pub async fn is_set_low(&mut self) -> Result<bool, PinError<I::Error>> {
+		self.is_set_high().await.map(|high| !high)
+	}
+
+	pub async fn toggle(&mut self) -> Result<(), PinError<I::Error>> {
+		self.device.lock().await.toggle_pin(self.index).await.map_err(PinError::Bus)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloc::vec;
+	use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+	use crate::devices::pca9557::enums::Address;
+
+	const ADDRESS: u8 = 0x18;
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_split_pins() {
+		use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+		use crate::test_utils::block_on;
+
+		let expectations = [
+			// The power-on polarity inversion of IO4 to IO7 is cleared
+			Transaction::write(ADDRESS, vec![0x02, 0x00]),
+			// IO5 becomes an output
+			Transaction::write(ADDRESS, vec![0x03, 0xDF]),
+			Transaction::write(ADDRESS, vec![0x01, 0x20]),
+			// IO2 is read as input
+			Transaction::write_read(ADDRESS, vec![0x00], vec![0x04]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let expander = Mutex::<NoopRawMutex, _>::new(PCA9557::new_i2c(i2c.clone(), Address::Primary).unwrap());
+
+		block_on(async {
+			let [_, _, mut io2, _, _, io5, _, _] = PCA9557::split(&expander).await.unwrap();
+			let mut io5 = io5.into_output().await.unwrap();
+			io5.set_high().await.unwrap();
+			assert!(io2.is_high().await.unwrap());
+		});
+		i2c.done();
+	}
+
+	#[cfg(feature = "sync")]
+	#[test]
+	fn test_input_polarity() {
+		use embedded_hal::digital::InputPin;
+
+		let expectations = [
+			Transaction::write(ADDRESS, vec![0x02, 0x00]),
+			Transaction::write(ADDRESS, vec![0x03, 0xEF]),
+			// IO4 is inverted again, going back to an input clears it
+			Transaction::write(ADDRESS, vec![0x02, 0x10]),
+			Transaction::write(ADDRESS, vec![0x02, 0x00]),
+			Transaction::write(ADDRESS, vec![0x03, 0xFF]),
+			Transaction::write_read(ADDRESS, vec![0x00], vec![0x10]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let expander = RefCell::new(PCA9557Sync::new_i2c(i2c.clone(), Address::Primary).unwrap());
+		let [_, _, _, _, io4, ..] = PCA9557Sync::split(&expander).unwrap();
+		let io4 = io4.into_output().unwrap();
+		expander.borrow_mut().write_polarity_raw(0x10).unwrap();
+		let mut io4 = io4.into_input().unwrap();
+		assert!(io4.is_high().unwrap());
+		i2c.done();
+	}
+
+	#[cfg(feature = "sync")]
+	#[test]
+	fn test_split_pins_sync() {
+		use embedded_hal::digital::{OutputPin, PinState, StatefulOutputPin};
+
+		let expectations = [
+			Transaction::write(ADDRESS, vec![0x02, 0x00]),
+			// IO0 becomes a high output without glitching low first
+			Transaction::write(ADDRESS, vec![0x01, 0x01]),
+			Transaction::write(ADDRESS, vec![0x03, 0xFE]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let expander = RefCell::new(PCA9557Sync::new_i2c(i2c.clone(), Address::Primary).unwrap());
+		let [io0, ..] = PCA9557Sync::split(&expander).unwrap();
+
+		let mut io0 = io0.into_output_with_state(PinState::High).unwrap();
+		assert!(io0.is_set_high().unwrap());
+		// Setting the level it already has does not touch the bus
+		io0.set_high().unwrap();
+		i2c.done();
+	}
+}
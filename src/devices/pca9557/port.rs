@@ -0,0 +1,190 @@
+//! [`OutputPort`] implementations for the [`PCA9557`] and arrays of its output pin handles,
+//! so it can drive the outputs of other drivers such as
+//! [`crate::devices::hd44780::Hd44780`].
+//!
+//! The device keeps the register selected by the command byte for all data bytes of a
+//! write, so up to four values go out in a single I2C write and the shadow is updated once.
+//! A single value that leaves the outputs unchanged is skipped.
+#[cfg(feature = "async")]
+use embassy_sync::blocking_mutex::raw::RawMutex;
+#[cfg(feature = "async")]
+use embedded_registers::RegisterInterfaceAsync;
+#[cfg(feature = "sync")]
+use embedded_registers::RegisterInterfaceSync;
+#[cfg(feature = "async")]
+use crate::devices::hd44780::port::OutputPort;
+#[cfg(feature = "sync")]
+use crate::devices::hd44780::port::OutputPortSync;
+#[cfg(feature = "async")]
+use crate::devices::pca9557::{pins::Pin, PCA9557};
+#[cfg(feature = "sync")]
+use crate::devices::pca9557::{pins::PinSync, PCA9557Sync};
+use crate::devices::expander::masked;
+use crate::devices::pca9557::pins::{Output, PinError};
+use crate::devices::pca9557::registers::OutputSequence;
+
+/// Number of values sent in one write of [`OutputSequence`]
+const SEQUENCE_LENGTH: usize = 4;
+
+#[maybe_async_cfg::maybe(
+	idents(PCA9557(async), RegisterInterface),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> PCA9557<I> {
+	/// Drives the outputs selected by `mask` to each of `values` in turn, four values per
+	/// write. A shorter last write repeats its last value, which leaves the outputs as they
+	/// are.
+	async fn write_output_sequence(&mut self, mask: u8, values: &[u8]) -> Result<(), I::Error> {
+		if let [value] = values {
+			return self.set_pins_masked(mask, *value).await;
+		}
+		for chunk in values.chunks(SEQUENCE_LENGTH) {
+			let output = self.output as u16;
+			let last = chunk[chunk.len() - 1];
+			let values: [u8; SEQUENCE_LENGTH] = core::array::from_fn(|n| {
+				masked(output, mask as u16, *chunk.get(n).unwrap_or(&last) as u16) as u8
+			});
+			self.write_register(OutputSequence { data: values }).await?;
+			self.output = values[SEQUENCE_LENGTH - 1];
+		}
+		Ok(())
+	}
+}
+
+#[maybe_async_cfg::maybe(
+	idents(OutputPort(async), PCA9557(async), RegisterInterface),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> OutputPort for PCA9557<I> {
+	type Error = I::Error;
+
+	async fn write_masked(&mut self, mask: u8, values: &[u8]) -> Result<(), Self::Error> {
+		self.write_output_sequence(mask, values).await
+	}
+
+	/// Clears the bits of `mask` in the `Configuration` register, all pins are inputs after
+	/// power-on.
+	///
+	/// # Panics
+	/// If `mask` selects an output that can only drive low, see
+	/// [`crate::devices::pca9557::model::Model::open_drain_pins`].
+	async fn enable_outputs(&mut self, mask: u8) -> Result<(), Self::Error> {
+		assert!(mask & self.model.open_drain_pins() == 0, "open-drain pin can not drive high");
+		self.write_configuration(self.configuration & !mask).await
+	}
+}
+
+/// Moves bit n of `value` to the bit of IO `indices[n]`
+fn to_device<const N: usize>(indices: [u8; N], value: u8) -> u8 {
+	indices
+		.iter()
+		.enumerate()
+		.filter(|(n, _)| value & (1 << n) != 0)
+		.fold(0, |device, (_, &index)| device | 1 << index)
+}
+
+/// Moves the bits of `mask` and of up to four values to the bits of the device IOs
+fn to_device_sequence<const N: usize>(indices: [u8; N], mask: u8, values: &[u8]) -> (u8, [u8; SEQUENCE_LENGTH]) {
+	let values = core::array::from_fn(|n| values.get(n).map_or(0, |&value| to_device(indices, value)));
+	(to_device(indices, mask), values)
+}
+
+#[cfg(feature = "sync")]
+impl<I: RegisterInterfaceSync, const N: usize> OutputPortSync for [PinSync<'_, I, Output>; N] {
+	type Error = PinError<I::Error>;
+
+	/// # Panics
+	/// If the pins do not belong to the same device.
+	fn write_masked(&mut self, mask: u8, values: &[u8]) -> Result<(), Self::Error> {
+		let Some(first) = self.first() else {
+			return Ok(());
+		};
+		let device = first.device;
+		assert!(self.iter().all(|pin| core::ptr::eq(pin.device, device)), "pins of different devices");
+		let indices: [u8; N] = core::array::from_fn(|n| self[n].index);
+		let mut device = device.borrow_mut();
+		for chunk in values.chunks(SEQUENCE_LENGTH) {
+			let (mask, values) = to_device_sequence(indices, mask, chunk);
+			device.write_output_sequence(mask, &values[..chunk.len()]).map_err(PinError::Bus)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(feature = "async")]
+impl<M: RawMutex, I: RegisterInterfaceAsync, const N: usize> OutputPort for [Pin<'_, M, I, Output>; N] {
+	type Error = PinError<I::Error>;
+
+	/// Keeps the device locked until all values are written.
+	///
+	/// # Panics
+	/// If the pins do not belong to the same device.
+	async fn write_masked(&mut self, mask: u8, values: &[u8]) -> Result<(), Self::Error> {
+		let Some(first) = self.first() else {
+			return Ok(());
+		};
+		let device = first.device;
+		assert!(self.iter().all(|pin| core::ptr::eq(pin.device, device)), "pins of different devices");
+		let indices: [u8; N] = core::array::from_fn(|n| self[n].index);
+		let mut device = device.lock().await;
+		for chunk in values.chunks(SEQUENCE_LENGTH) {
+			let (mask, values) = to_device_sequence(indices, mask, chunk);
+			device.write_output_sequence(mask, &values[..chunk.len()]).await.map_err(PinError::Bus)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_pin_order() {
+		assert_eq!(to_device([7, 2, 0], 0b011), 0x84);
+		assert_eq!(to_device([7, 2, 0], 0b100), 0x01);
+	}
+
+	#[cfg(feature = "sync")]
+	#[test]
+	#[should_panic(expected = "open-drain pin")]
+	fn test_open_drain_output() {
+		use embedded_hal_mock::eh1::i2c::Mock;
+		use crate::devices::pca9557::enums::Address;
+
+		let mut i2c = Mock::new(&[]);
+		let mut expander = PCA9557Sync::new_i2c(i2c.clone(), Address::Primary).unwrap();
+		i2c.done();
+		let _ = expander.enable_outputs(0x03);
+	}
+
+	#[cfg(feature = "sync")]
+	#[test]
+	fn test_pin_port_sync() {
+		use alloc::vec;
+		use core::cell::RefCell;
+		use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+		use crate::devices::pca9557::enums::Address;
+
+		// Bit 0 of the port is IO6 and bit 1 is IO3, the values share one write and the last
+		// one is repeated to fill it
+		let expectations = [
+			Transaction::write(0x18, vec![0x02, 0x00]),
+			Transaction::write(0x18, vec![0x03, 0xBF]),
+			Transaction::write(0x18, vec![0x03, 0xB7]),
+			Transaction::write(0x18, vec![0x01, 0x40, 0x40, 0x48, 0x48]),
+			Transaction::write(0x18, vec![0x01, 0x08]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let expander = RefCell::new(PCA9557Sync::new_i2c(i2c.clone(), Address::Primary).unwrap());
+		let [_, _, _, io3, _, _, io6, _] = PCA9557Sync::split(&expander).unwrap();
+		let mut port = [io6.into_output().unwrap(), io3.into_output().unwrap()];
+		port.write_masked(0b11, &[0b01, 0b01, 0b11]).unwrap();
+		// A single value is a plain write, skipped if nothing changes
+		port.write_masked(0b01, &[0b00]).unwrap();
+		port.write_masked(0b01, &[0b00]).unwrap();
+		i2c.done();
+	}
+}
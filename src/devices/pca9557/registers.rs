@@ -23,6 +23,16 @@ pub struct OutputPort {
 	pub pins: [LogicLevel;8]
 }
 
+/// Four values written to the `OutputPort` register in one transaction. The device keeps the
+/// register selected by the command byte for all data bytes that follow, so the outputs take
+/// each value in turn.
+#[device_register(super::PCA9557)]
+#[register(address = 0x01, mode = "w")]
+#[bondrewd(read_from = "msb0", default_endianness = "be", enforce_bytes = 4)]
+pub struct OutputSequence {
+	pub values: [u8; 4]
+}
+
 #[derive(BitfieldEnum, Copy, Clone, Default, PartialEq, Eq, Debug, defmt::Format)]
 #[bondrewd_enum(u8)]
 pub enum Polarity {
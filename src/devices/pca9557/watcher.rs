@@ -0,0 +1,234 @@
+//! Change detection on the inputs of a [`PCA9557`] by polling, the device has no interrupt
+//! output.
+//!
+//! ```ignore
+//! let mut watcher = InputWatcher::new(10_000, 3);
+//! loop {
+//!     let edges = expander.wait_for_change(&mut watcher, 0b0000_0011, &mut delay).await?;
+//!     if edges.edge(0) == Some(Edge::Falling) {
+//!         // button on IO0 pressed
+//!     }
+//! }
+//! ```
+#[cfg(feature = "async")]
+use embedded_registers::RegisterInterfaceAsync;
+#[cfg(feature = "sync")]
+use embedded_registers::RegisterInterfaceSync;
+#[cfg(feature = "async")]
+use crate::devices::pca9557::PCA9557;
+#[cfg(feature = "sync")]
+use crate::devices::pca9557::PCA9557Sync;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum Edge {
+	Rising,
+	Falling,
+}
+
+/// The debounced changes of one poll, bit n belongs to IO n
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, defmt::Format)]
+pub struct Edges {
+	pub rising: u8,
+	pub falling: u8,
+}
+
+impl Edges {
+	/// Pins that changed in either direction
+	pub fn changed(&self) -> u8 {
+		self.rising | self.falling
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.changed() == 0
+	}
+
+	/// The edge of IO `n`, if it changed
+	pub fn edge(&self, n: u8) -> Option<Edge> {
+		if self.rising & (1 << n) != 0 {
+			Some(Edge::Rising)
+		} else if self.falling & (1 << n) != 0 {
+			Some(Edge::Falling)
+		} else {
+			None
+		}
+	}
+
+	/// Only the edges of the pins selected by `mask`
+	pub fn masked(&self, mask: u8) -> Self {
+		Self {
+			rising: self.rising & mask,
+			falling: self.falling & mask,
+		}
+	}
+
+	/// The edges of both, a pin that changed back and forth has both edges set
+	pub fn merged(&self, other: &Self) -> Self {
+		Self {
+			rising: self.rising | other.rising,
+			falling: self.falling | other.falling,
+		}
+	}
+}
+
+/// Keeps the debounced state of the inputs between polls.
+///
+/// A pin only changes its debounced state once its new level was seen in `debounce`
+/// consecutive polls, so the debounce time is `debounce` times the poll interval.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub struct InputWatcher {
+	interval_us: u32,
+	debounce: u8,
+	/// Debounced levels, `None` before the first poll
+	state: Option<u8>,
+	/// Number of consecutive polls each pin differed from its debounced level
+	pending: [u8; 8],
+	/// Edges seen by [`PCA9557::wait_for_change`] on pins outside its mask
+	unreported: Edges,
+}
+
+impl InputWatcher {
+	/// Creates a watcher polling every `interval_us` microseconds in
+	/// [`PCA9557::wait_for_change`]. A `debounce` of 0 or 1 reports every change right away.
+	pub fn new(interval_us: u32, debounce: u8) -> Self {
+		Self {
+			interval_us,
+			debounce,
+			state: None,
+			pending: [0; 8],
+			unreported: Edges::default(),
+		}
+	}
+
+	/// The debounced levels, bit n belongs to IO n
+	pub fn state(&self) -> Option<u8> {
+		self.state
+	}
+
+	/// Returns and clears the edges [`PCA9557::wait_for_change`] saw on pins outside its
+	/// mask
+	pub fn take_unreported(&mut self) -> Edges {
+		core::mem::take(&mut self.unreported)
+	}
+
+	/// Feeds the raw value of the `InputPort` register. The first value is taken as the
+	/// initial state without reporting edges.
+	pub fn update(&mut self, raw: u8) -> Edges {
+		let Some(state) = self.state else {
+			self.state = Some(raw);
+			return Edges::default();
+		};
+
+		let mut changed = 0;
+		for (n, pending) in self.pending.iter_mut().enumerate() {
+			if (raw ^ state) & (1 << n) == 0 {
+				*pending = 0;
+				continue;
+			}
+			*pending = pending.saturating_add(1);
+			if *pending >= self.debounce {
+				*pending = 0;
+				changed |= 1 << n;
+			}
+		}
+
+		let new_state = state ^ changed;
+		self.state = Some(new_state);
+		Edges {
+			rising: changed & new_state,
+			falling: changed & !new_state,
+		}
+	}
+}
+
+#[maybe_async_cfg::maybe(
+	idents(hal(sync = "embedded_hal", async = "embedded_hal_async"), PCA9557(async), RegisterInterface),
+	sync(feature = "sync"),
+	async(feature = "async")
+)]
+impl<I: RegisterInterface> PCA9557<I> {
+	/// Reads the inputs once and returns the debounced edges
+	pub async fn poll_inputs(&mut self, watcher: &mut InputWatcher) -> Result<Edges, I::Error> {
+		let raw = self.read_input_raw().await?;
+		Ok(watcher.update(raw))
+	}
+
+	/// Polls the inputs at the interval of the watcher until one of the pins selected by
+	/// `mask` changed, returns the edges of those pins.
+	///
+	/// Edges of the other pins stay pending in the watcher. A later call returns them right
+	/// away once its mask selects them, or they can be taken with
+	/// [`InputWatcher::take_unreported`].
+	pub async fn wait_for_change<D: hal::delay::DelayNs>(&mut self, watcher: &mut InputWatcher, mask: u8, delay: &mut D) -> Result<Edges, I::Error> {
+		loop {
+			let edges = watcher.unreported.masked(mask);
+			if !edges.is_empty() {
+				watcher.unreported = watcher.unreported.masked(!mask);
+				return Ok(edges);
+			}
+			let edges = self.poll_inputs(watcher).await?;
+			watcher.unreported = watcher.unreported.merged(&edges);
+			if watcher.unreported.masked(mask).is_empty() {
+				delay.delay_us(watcher.interval_us).await;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_edges() {
+		let mut watcher = InputWatcher::new(1000, 1);
+		assert!(watcher.update(0b0000_0101).is_empty());
+		let edges = watcher.update(0b0000_0110);
+		assert_eq!(edges, Edges { rising: 0b0000_0010, falling: 0b0000_0001 });
+		assert_eq!(edges.edge(1), Some(Edge::Rising));
+		assert_eq!(edges.edge(0), Some(Edge::Falling));
+		assert_eq!(edges.edge(2), None);
+	}
+
+	#[test]
+	fn test_debounce() {
+		let mut watcher = InputWatcher::new(1000, 3);
+		watcher.update(0x00);
+		// A bouncing contact settles after a few polls
+		assert!(watcher.update(0x01).is_empty());
+		assert!(watcher.update(0x00).is_empty());
+		assert!(watcher.update(0x01).is_empty());
+		assert!(watcher.update(0x01).is_empty());
+		assert_eq!(watcher.update(0x01).rising, 0x01);
+		assert_eq!(watcher.state(), Some(0x01));
+		assert!(watcher.update(0x01).is_empty());
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_wait_for_change() {
+		use alloc::vec;
+		use embedded_hal_mock::eh1::delay::NoopDelay;
+		use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+		use crate::devices::pca9557::enums::Address;
+		use crate::test_utils::block_on;
+
+		// IO7 changes first, but only IO0 is watched
+		let expectations = [
+			Transaction::write_read(0x18, vec![0x00], vec![0x01]),
+			Transaction::write_read(0x18, vec![0x00], vec![0x81]),
+			Transaction::write_read(0x18, vec![0x00], vec![0x80]),
+		];
+		let mut i2c = Mock::new(&expectations);
+		let mut expander = PCA9557::new_i2c(i2c.clone(), Address::Primary).unwrap();
+		let mut watcher = InputWatcher::new(1000, 1);
+		let edges = block_on(expander.wait_for_change(&mut watcher, 0x01, &mut NoopDelay)).unwrap();
+		assert_eq!(edges, Edges { rising: 0, falling: 0x01 });
+		assert_eq!(watcher.state(), Some(0x80));
+
+		// The edge of IO7 was kept and is returned without polling again
+		let edges = block_on(expander.wait_for_change(&mut watcher, 0x80, &mut NoopDelay)).unwrap();
+		assert_eq!(edges, Edges { rising: 0x80, falling: 0 });
+		assert!(watcher.take_unreported().is_empty());
+		i2c.done();
+	}
+}
@@ -1,8 +1,13 @@
-#![feature(generic_arg_infer)]
 #![no_std]
 extern crate alloc;
 
+#[cfg(not(any(feature = "sync", feature = "async")))]
+compile_error!("At least one of the features `sync` or `async` has to be enabled");
+
 pub mod devices;
 pub mod common;
+pub mod altimetry;
 mod packet;
+#[cfg(all(test, feature = "async"))]
+mod test_utils;
 
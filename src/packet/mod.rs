@@ -37,6 +37,7 @@ pub trait DataPacket{
 #[repr(u8)]
 pub enum PacketType{
 	Accelerometer = 0x01,
+	FlightEvent = 0x02,
 	Test = 0xFF,
 }
 
@@ -66,11 +67,27 @@ pub struct TestPacket {
 
 impl_packet!(TestPacket,PacketType::Test);
 
+/// A flight event, see [`crate::altimetry::flight::FlightEvent`]
+#[derive(Bitfields, Copy, Clone)]
+#[bondrewd(default_endianness = "le")]
+pub struct FlightEventPacket {
+	pub event:u8,
+	/// Time of the event in milliseconds
+	pub time_ms:u32,
+	/// Altitude above ground in centimeters
+	pub altitude_cm:i32,
+	/// Vertical velocity in centimeters per second
+	pub velocity_cm_s:i32,
+}
+
+impl_packet!(FlightEventPacket,PacketType::FlightEvent);
+
 
 impl From<PacketType> for u8 {
 	fn from(packet_type: PacketType) -> Self {
 		match packet_type {
 			PacketType::Accelerometer => 0x00,
+			PacketType::FlightEvent => 0x02,
 			PacketType::Test => 0xFF,
 		}
 	}
@@ -0,0 +1,15 @@
+//! Helpers shared by the tests of several modules.
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+
+/// Runs a future to completion. The bus mocks never pend, so polling in a loop is enough.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+	let mut future = pin!(future);
+	let mut context = Context::from_waker(Waker::noop());
+	loop {
+		if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+			return output;
+		}
+	}
+}